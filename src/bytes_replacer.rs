@@ -1,139 +1,94 @@
-use crate::case_matcher::match_case;
-use crate::utils::{get_be_16_bytes, get_le_16_bytes};
+use crate::callback::WordContext;
+use crate::encoding::Encoding;
+use crate::error::ReplaceError;
+use crate::replacer::Replacer;
+use std::borrow::Cow;
 use std::collections::HashMap;
 
-pub fn replace_ascii(dictionary: &HashMap<String, String>, bytes: &[u8]) -> (u128, Vec<u8>) {
-    let mut new_bytes: Vec<u8> = Vec::with_capacity(bytes.len());
-    let mut cursor = 0;
-    let mut count = 0;
-    while cursor < bytes.len() {
-        let char = char::from(bytes[cursor]);
-        if char.is_ascii_alphanumeric() {
-            let word: String = bytes[cursor..]
-                .iter()
-                .map(|&b| char::from(b))
-                .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
-                .collect();
-
-            cursor += word.len();
-            let new_word = match dictionary.get(&word.to_lowercase()) {
-                Some(value) => {
-                    count += 1;
-                    &match_case(&word, value)
-                }
-                None => &word,
-            };
-
-            new_bytes.extend_from_slice(new_word.as_bytes());
-        } else {
-            new_bytes.push(bytes[cursor]);
-            cursor += 1;
-        }
-    }
+pub fn replace_ascii(
+    dictionary: &HashMap<String, String>,
+    bytes: &[u8],
+) -> Result<(u128, Vec<u8>), ReplaceError> {
+    Ok(strict(dictionary)?.replace_ascii(bytes))
+}
 
-    (count, new_bytes)
+pub fn replace_le_16(
+    dictionary: &HashMap<String, String>,
+    bytes: &[u8],
+) -> Result<(u128, Vec<u8>), ReplaceError> {
+    Ok(strict(dictionary)?.replace_le_16(bytes))
 }
 
-pub fn replace_le_16(dictionary: &HashMap<String, String>, bytes: &[u8]) -> (u128, Vec<u8>) {
-    let mut new_bytes: Vec<u8> = Vec::with_capacity(bytes.len());
-    let mut cursor = 0;
-    let mut count = 0;
-    while cursor < bytes.len() - 1 {
-        let u16_bytes = u16::from_le_bytes([bytes[cursor], bytes[cursor + 1]]);
-        let char = char::from_u32(u16_bytes as u32).unwrap_or(';');
-        if char.is_ascii_alphanumeric() {
-            let mut chars: Vec<char> = vec![];
-            while cursor < bytes.len() - 1 {
-                let u16_bytes = u16::from_le_bytes([bytes[cursor], bytes[cursor + 1]]);
-                let char = char::from_u32(u16_bytes as u32).unwrap_or(';');
-                if char.is_ascii_alphanumeric() || char == '_' {
-                    cursor += 2;
-                    chars.push(char);
-                } else {
-                    break;
-                }
-            }
-
-            let word = chars.iter().collect::<String>();
-            let new_word = match dictionary.get(&word.to_lowercase()) {
-                Some(value) => {
-                    count += 1;
-                    &match_case(&word, value)
-                }
-                None => &word,
-            };
-
-            let word_u16_bytes: Vec<u8> = get_le_16_bytes(new_word);
-            word_u16_bytes.into_iter().for_each(|b| new_bytes.push(b));
-        } else {
-            new_bytes.push(bytes[cursor]);
-            cursor += 1;
-
-            if cursor == bytes.len() - 1 {
-                new_bytes.push(bytes[cursor]);
-            }
-        }
-    }
+pub fn replace_be_16(
+    dictionary: &HashMap<String, String>,
+    bytes: &[u8],
+) -> Result<(u128, Vec<u8>), ReplaceError> {
+    Ok(strict(dictionary)?.replace_be_16(bytes))
+}
 
-    (count, new_bytes)
+pub fn replace_le_32(
+    dictionary: &HashMap<String, String>,
+    bytes: &[u8],
+) -> Result<(u128, Vec<u8>), ReplaceError> {
+    Ok(strict(dictionary)?.replace_le_32(bytes))
 }
 
-pub fn replace_be_16(dictionary: &HashMap<String, String>, bytes: &[u8]) -> (u128, Vec<u8>) {
-    let mut new_bytes: Vec<u8> = Vec::with_capacity(bytes.len());
-    let mut cursor = 0;
-    let mut count = 0;
-    while cursor < bytes.len() - 1 {
-        let u16_bytes = u16::from_be_bytes([bytes[cursor], bytes[cursor + 1]]);
-        let char = char::from_u32(u16_bytes as u32).unwrap_or(';');
-        if char.is_ascii_alphanumeric() {
-            let mut chars: Vec<char> = vec![];
-            while cursor < bytes.len() - 1 {
-                let u16_bytes = u16::from_be_bytes([bytes[cursor], bytes[cursor + 1]]);
-                let char = char::from_u32(u16_bytes as u32).unwrap_or(';');
-                if char.is_ascii_alphanumeric() || char == '_' {
-                    cursor += 2;
-                    chars.push(char);
-                } else {
-                    break;
-                }
-            }
-
-            let word = chars.iter().collect::<String>();
-            let new_word = match dictionary.get(&word.to_lowercase()) {
-                Some(value) => {
-                    count += 1;
-                    &match_case(&word, value)
-                }
-                None => &word,
-            };
-
-            let word_u16_bytes: Vec<u8> = get_be_16_bytes(new_word);
-            word_u16_bytes.into_iter().for_each(|b| new_bytes.push(b));
-        } else {
-            new_bytes.push(bytes[cursor]);
-            cursor += 1;
-
-            if cursor == bytes.len() - 1 {
-                new_bytes.push(bytes[cursor]);
-            }
-        }
-    }
+pub fn replace_be_32(
+    dictionary: &HashMap<String, String>,
+    bytes: &[u8],
+) -> Result<(u128, Vec<u8>), ReplaceError> {
+    Ok(strict(dictionary)?.replace_be_32(bytes))
+}
+
+pub fn replace_auto(
+    dictionary: &HashMap<String, String>,
+    bytes: &[u8],
+) -> Result<(Encoding, u128, Vec<u8>), ReplaceError> {
+    Ok(strict(dictionary)?.replace_auto(bytes))
+}
+
+pub fn replace_unity(
+    dictionary: &HashMap<String, String>,
+    bytes: &[u8],
+) -> Result<(u128, Vec<u8>), ReplaceError> {
+    strict(dictionary)?.replace_unity(bytes)
+}
+
+// These functions predate `ReplacerBuilder::strict`, and report a key that can never match
+// rather than leave it out without a word.
+fn strict(dictionary: &HashMap<String, String>) -> Result<Replacer, ReplaceError> {
+    Replacer::builder().strict(true).build(dictionary)
+}
 
-    (count, new_bytes)
+/// Asks `replacement` for every word instead of looking it up in a dictionary. Words it returns
+/// `None` for are kept. `Replacer::replace_with` does the same with other word and case rules.
+pub fn replace_with<'r>(
+    bytes: &[u8],
+    encoding: Encoding,
+    replacement: impl FnMut(&str, &WordContext) -> Option<Cow<'r, str>>,
+) -> (u128, Vec<u8>) {
+    let replacer = Replacer::new(&HashMap::new()).expect("an empty dictionary always builds");
+    replacer.replace_with(bytes, encoding, replacement)
 }
 
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
 
-    use crate::bytes_replacer::{replace_ascii, replace_be_16, replace_le_16};
-    use crate::utils::{get_be_16_bytes, get_le_16_bytes};
+    use crate::bytes_replacer::{
+        replace_ascii, replace_auto, replace_be_16, replace_be_32, replace_le_16, replace_le_32,
+        replace_with,
+    };
+    use crate::encoding::Encoding;
+    use crate::error::ReplaceError;
+    use crate::replacer::Replacer;
+    use crate::utils::{get_be_16_bytes, get_be_32_bytes, get_le_16_bytes, get_le_32_bytes};
 
     #[test]
     fn replaces_text_le() {
         let map = create_map();
         let content = get_le_16_bytes("lorem pondem first");
-        let (count, result) = replace_le_16(&map, &content);
+        let (count, result) = replace_le_16(&map, &content).unwrap();
         assert_eq!(get_le_16_bytes("lorem pondem changed"), result);
         assert_eq!(count, 1);
     }
@@ -142,7 +97,7 @@ mod tests {
     fn works_with_stoppers_le() {
         let map = create_map();
         let content = get_le_16_bytes("lorem pondem first.");
-        let (count, result) = replace_le_16(&map, &content);
+        let (count, result) = replace_le_16(&map, &content).unwrap();
         assert_eq!(get_le_16_bytes("lorem pondem changed."), result);
         assert_eq!(count, 1);
     }
@@ -151,7 +106,7 @@ mod tests {
     fn change_more_words_le() {
         let map = create_map();
         let content = get_le_16_bytes("first and another.");
-        let (count, result) = replace_le_16(&map, &content);
+        let (count, result) = replace_le_16(&map, &content).unwrap();
         assert_eq!(get_le_16_bytes("changed and something."), result);
         assert_eq!(count, 2);
     }
@@ -160,7 +115,7 @@ mod tests {
     fn correctly_works_with_plural_le() {
         let map = create_map();
         let content = get_le_16_bytes("first and another, anothers.");
-        let (count, result) = replace_le_16(&map, &content);
+        let (count, result) = replace_le_16(&map, &content).unwrap();
         assert_eq!(
             get_le_16_bytes("changed and something, somethingelse."),
             result
@@ -172,7 +127,7 @@ mod tests {
     fn new_lines_work_le() {
         let map = create_map();
         let content = get_le_16_bytes("\nfirst and \nAnother, \nANOTHERS.");
-        let (count, result) = replace_le_16(&map, &content);
+        let (count, result) = replace_le_16(&map, &content).unwrap();
         assert_eq!(
             get_le_16_bytes("\nchanged and \nSomething, \nSOMETHINGELSE."),
             result
@@ -184,7 +139,7 @@ mod tests {
     fn capilization_le() {
         let map = create_map();
         let content = get_le_16_bytes("First Another Capital Anothers");
-        let (_, result) = replace_le_16(&map, &content);
+        let (_, result) = replace_le_16(&map, &content).unwrap();
         assert_eq!(
             get_le_16_bytes("Changed Something Toonice Somethingelse"),
             result
@@ -195,7 +150,7 @@ mod tests {
     fn uppercase_le() {
         let map = create_map();
         let content = get_le_16_bytes("FIRST ANOTHER CAPITAL ANOTHERS");
-        let (_, result) = replace_le_16(&map, &content);
+        let (_, result) = replace_le_16(&map, &content).unwrap();
         assert_eq!(
             get_le_16_bytes("CHANGED SOMETHING TOONICE SOMETHINGELSE"),
             result
@@ -206,7 +161,7 @@ mod tests {
     fn lowercase_le() {
         let map = create_map();
         let content = get_le_16_bytes("first another capital anothers");
-        let (count, result) = replace_le_16(&map, &content);
+        let (count, result) = replace_le_16(&map, &content).unwrap();
         assert_eq!(
             get_le_16_bytes("changed something toonice somethingelse"),
             result
@@ -218,7 +173,7 @@ mod tests {
     fn replacment_is_bigger_than_original_le() {
         let map = create_map();
         let content = get_le_16_bytes("small");
-        let (count, result) = replace_le_16(&map, &content);
+        let (count, result) = replace_le_16(&map, &content).unwrap();
         assert_eq!(get_le_16_bytes("this is bigger than the original"), result);
         assert_eq!(count, 1);
     }
@@ -229,7 +184,7 @@ mod tests {
         let content = get_le_16_bytes(
             "1 string m_Localized = \"Chinese (中文)\"\n0 MetadataCollection m_Metadata\n1 string m_Localized = \"Russian (Русский)\"\n0 MetadataCollection m_Metadata",
         );
-        let (count, result) = replace_le_16(&map, &content);
+        let (count, result) = replace_le_16(&map, &content).unwrap();
         assert_eq!(content, result);
         assert_eq!(count, 0);
     }
@@ -243,7 +198,7 @@ mod tests {
         let content = [prefix.clone(), content].concat();
         let expected = [prefix, expected].concat();
 
-        let (count, result) = replace_le_16(&map, &content);
+        let (count, result) = replace_le_16(&map, &content).unwrap();
         assert_eq!(expected, result);
         assert_eq!(count, 1);
     }
@@ -257,7 +212,7 @@ mod tests {
         let content = [content, prefix.clone()].concat();
         let expected = [expected, prefix].concat();
 
-        let (count, result) = replace_le_16(&map, &content);
+        let (count, result) = replace_le_16(&map, &content).unwrap();
         assert_eq!(expected, result);
         assert_eq!(count, 1);
     }
@@ -292,16 +247,99 @@ mod tests {
         ]
         .concat();
 
-        let (count, result) = replace_le_16(&map, &content);
+        let (count, result) = replace_le_16(&map, &content).unwrap();
         assert_eq!(expected, result);
         assert_eq!(count, 3);
     }
 
+    #[test]
+    fn empty_input_le() {
+        let map = create_map();
+        let (count, result) = replace_le_16(&map, &[]).unwrap();
+        assert!(result.is_empty());
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn single_byte_input_le() {
+        let map = create_map();
+        let (count, result) = replace_le_16(&map, &[7]).unwrap();
+        assert_eq!(vec![7], result);
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn replaces_non_ascii_words_le() {
+        let map = create_non_ascii_map();
+        let content = get_le_16_bytes("Chinese (中文), русский, Café");
+        let (count, result) = replace_le_16(&map, &content).unwrap();
+        assert_eq!(
+            get_le_16_bytes("Chinese (汉语), российский, Bistro"),
+            result
+        );
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn keeps_non_ascii_case_le() {
+        let map = create_non_ascii_map();
+        let content = get_le_16_bytes("РУССКИЙ Русский CAFÉ");
+        let (count, result) = replace_le_16(&map, &content).unwrap();
+        assert_eq!(get_le_16_bytes("РОССИЙСКИЙ Российский BISTRO"), result);
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn replaces_surrogate_pairs_le() {
+        let map = create_non_ascii_map();
+        let content = get_le_16_bytes("first 𝒜𝒷𝒸 𠀀");
+        let (count, result) = replace_le_16(&map, &content).unwrap();
+        assert_eq!(get_le_16_bytes("changed 𝔄𝔟𝔠 𠀀"), result);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn keeps_unpaired_surrogates_le() {
+        let map = create_non_ascii_map();
+        let content = [
+            get_le_16_bytes("first "),
+            vec![0x00, 0xD8],
+            get_le_16_bytes("first "),
+            vec![0x00, 0xDC],
+        ]
+        .concat();
+        let expected = [
+            get_le_16_bytes("changed "),
+            vec![0x00, 0xD8],
+            get_le_16_bytes("changed "),
+            vec![0x00, 0xDC],
+        ]
+        .concat();
+        let (count, result) = replace_le_16(&map, &content).unwrap();
+        assert_eq!(expected, result);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn non_ascii_bytes_around_words_le() {
+        let map = create_non_ascii_map();
+        let content = [vec![22, 1, 255], get_le_16_bytes("café"), vec![22, 1, 255]].concat();
+        let expected = [
+            vec![22, 1, 255],
+            get_le_16_bytes("bistro"),
+            vec![22, 1, 255],
+        ]
+        .concat();
+        let (count, result) = replace_le_16(&map, &content).unwrap();
+        assert_eq!(expected, result);
+        assert_eq!(count, 1);
+    }
+
     #[test]
     fn replaces_text_be() {
         let map = create_map();
         let content = get_be_16_bytes("lorem pondem first");
-        let (count, result) = replace_be_16(&map, &content);
+        let (count, result) = replace_be_16(&map, &content).unwrap();
         assert_eq!(get_be_16_bytes("lorem pondem changed"), result);
         assert_eq!(count, 1);
     }
@@ -310,7 +348,7 @@ mod tests {
     fn works_with_stoppers_be() {
         let map = create_map();
         let content = get_be_16_bytes("lorem pondem first.");
-        let (count, result) = replace_be_16(&map, &content);
+        let (count, result) = replace_be_16(&map, &content).unwrap();
         assert_eq!(get_be_16_bytes("lorem pondem changed."), result);
         assert_eq!(count, 1);
     }
@@ -319,7 +357,7 @@ mod tests {
     fn change_more_words_be() {
         let map = create_map();
         let content = get_be_16_bytes("first and another.");
-        let (count, result) = replace_be_16(&map, &content);
+        let (count, result) = replace_be_16(&map, &content).unwrap();
         assert_eq!(get_be_16_bytes("changed and something."), result);
         assert_eq!(count, 2);
     }
@@ -328,7 +366,7 @@ mod tests {
     fn correctly_works_with_plural_be() {
         let map = create_map();
         let content = get_be_16_bytes("first and another, anothers.");
-        let (count, result) = replace_be_16(&map, &content);
+        let (count, result) = replace_be_16(&map, &content).unwrap();
         assert_eq!(
             get_be_16_bytes("changed and something, somethingelse."),
             result
@@ -340,7 +378,7 @@ mod tests {
     fn new_lines_work_be() {
         let map = create_map();
         let content = get_be_16_bytes("\nfirst and \nAnother, \nANOTHERS.");
-        let (count, result) = replace_be_16(&map, &content);
+        let (count, result) = replace_be_16(&map, &content).unwrap();
         assert_eq!(
             get_be_16_bytes("\nchanged and \nSomething, \nSOMETHINGELSE."),
             result
@@ -352,7 +390,7 @@ mod tests {
     fn capilization_be() {
         let map = create_map();
         let content = get_be_16_bytes("First Another Capital Anothers");
-        let (_, result) = replace_be_16(&map, &content);
+        let (_, result) = replace_be_16(&map, &content).unwrap();
         assert_eq!(
             get_be_16_bytes("Changed Something Toonice Somethingelse"),
             result
@@ -363,7 +401,7 @@ mod tests {
     fn uppercase_be() {
         let map = create_map();
         let content = get_be_16_bytes("FIRST ANOTHER CAPITAL ANOTHERS");
-        let (_, result) = replace_be_16(&map, &content);
+        let (_, result) = replace_be_16(&map, &content).unwrap();
         assert_eq!(
             get_be_16_bytes("CHANGED SOMETHING TOONICE SOMETHINGELSE"),
             result
@@ -374,7 +412,7 @@ mod tests {
     fn lowercase_be() {
         let map = create_map();
         let content = get_be_16_bytes("first another capital anothers");
-        let (count, result) = replace_be_16(&map, &content);
+        let (count, result) = replace_be_16(&map, &content).unwrap();
         assert_eq!(
             get_be_16_bytes("changed something toonice somethingelse"),
             result
@@ -386,7 +424,7 @@ mod tests {
     fn replacment_is_bigger_than_original_be() {
         let map = create_map();
         let content = get_be_16_bytes("small");
-        let (count, result) = replace_be_16(&map, &content);
+        let (count, result) = replace_be_16(&map, &content).unwrap();
         assert_eq!(get_be_16_bytes("this is bigger than the original"), result);
         assert_eq!(count, 1);
     }
@@ -397,7 +435,7 @@ mod tests {
         let content = get_be_16_bytes(
             "1 string m_Localized = \"Chinese (中文)\"\n0 MetadataCollection m_Metadata\n1 string m_Localized = \"Russian (Русский)\"\n0 MetadataCollection m_Metadata",
         );
-        let (count, result) = replace_be_16(&map, &content);
+        let (count, result) = replace_be_16(&map, &content).unwrap();
         assert_eq!(content, result);
         assert_eq!(count, 0);
     }
@@ -411,7 +449,7 @@ mod tests {
         let content = [prefix.clone(), content].concat();
         let expected = [prefix, expected].concat();
 
-        let (count, result) = replace_be_16(&map, &content);
+        let (count, result) = replace_be_16(&map, &content).unwrap();
         assert_eq!(expected, result);
         assert_eq!(count, 1);
     }
@@ -425,7 +463,7 @@ mod tests {
         let content = [content, prefix.clone()].concat();
         let expected = [expected, prefix].concat();
 
-        let (count, result) = replace_be_16(&map, &content);
+        let (count, result) = replace_be_16(&map, &content).unwrap();
         assert_eq!(expected, result);
         assert_eq!(count, 1);
     }
@@ -460,16 +498,42 @@ mod tests {
         ]
         .concat();
 
-        let (count, result) = replace_be_16(&map, &content);
+        let (count, result) = replace_be_16(&map, &content).unwrap();
         assert_eq!(expected, result);
         assert_eq!(count, 3);
     }
 
+    #[test]
+    fn empty_input_be() {
+        let map = create_map();
+        let (count, result) = replace_be_16(&map, &[]).unwrap();
+        assert!(result.is_empty());
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn single_byte_input_be() {
+        let map = create_map();
+        let (count, result) = replace_be_16(&map, &[7]).unwrap();
+        assert_eq!(vec![7], result);
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn invalid_key_is_reported_be() {
+        let mut map = create_map();
+        map.insert("...".into(), "dots".into());
+        assert!(matches!(
+            replace_be_16(&map, &get_be_16_bytes("first")),
+            Err(ReplaceError::InvalidKey(key)) if key == "..."
+        ));
+    }
+
     #[test]
     fn replaces_text_ascii() {
         let map = create_map();
         let content = get_ascii_bytes("lorem pondem first");
-        let (count, result) = replace_ascii(&map, &content);
+        let (count, result) = replace_ascii(&map, &content).unwrap();
         assert_eq!(get_ascii_bytes("lorem pondem changed"), result);
         assert_eq!(count, 1);
     }
@@ -478,7 +542,7 @@ mod tests {
     fn works_with_stoppers_ascii() {
         let map = create_map();
         let content = get_ascii_bytes("lorem pondem first.");
-        let (count, result) = replace_ascii(&map, &content);
+        let (count, result) = replace_ascii(&map, &content).unwrap();
         assert_eq!(get_ascii_bytes("lorem pondem changed."), result);
         assert_eq!(count, 1);
     }
@@ -487,7 +551,7 @@ mod tests {
     fn change_more_words_ascii() {
         let map = create_map();
         let content = get_ascii_bytes("first and another.");
-        let (count, result) = replace_ascii(&map, &content);
+        let (count, result) = replace_ascii(&map, &content).unwrap();
         assert_eq!(get_ascii_bytes("changed and something."), result);
         assert_eq!(count, 2);
     }
@@ -496,7 +560,7 @@ mod tests {
     fn correctly_works_with_plural_ascii() {
         let map = create_map();
         let content = get_ascii_bytes("first and another, anothers.");
-        let (count, result) = replace_ascii(&map, &content);
+        let (count, result) = replace_ascii(&map, &content).unwrap();
         assert_eq!(
             get_ascii_bytes("changed and something, somethingelse."),
             result
@@ -508,7 +572,7 @@ mod tests {
     fn new_lines_work_ascii() {
         let map = create_map();
         let content = get_ascii_bytes("\nfirst and \nAnother, \nANOTHERS.");
-        let (count, result) = replace_ascii(&map, &content);
+        let (count, result) = replace_ascii(&map, &content).unwrap();
         assert_eq!(
             get_ascii_bytes("\nchanged and \nSomething, \nSOMETHINGELSE."),
             result
@@ -520,7 +584,7 @@ mod tests {
     fn capilization_ascii() {
         let map = create_map();
         let content = get_ascii_bytes("First Another Capital Anothers");
-        let (_, result) = replace_ascii(&map, &content);
+        let (_, result) = replace_ascii(&map, &content).unwrap();
         assert_eq!(
             get_ascii_bytes("Changed Something Toonice Somethingelse"),
             result
@@ -531,7 +595,7 @@ mod tests {
     fn uppercase_ascii() {
         let map = create_map();
         let content = get_ascii_bytes("FIRST ANOTHER CAPITAL ANOTHERS");
-        let (_, result) = replace_ascii(&map, &content);
+        let (_, result) = replace_ascii(&map, &content).unwrap();
         assert_eq!(
             get_ascii_bytes("CHANGED SOMETHING TOONICE SOMETHINGELSE"),
             result
@@ -542,7 +606,7 @@ mod tests {
     fn lowercase_ascii() {
         let map = create_map();
         let content = get_ascii_bytes("first another capital anothers");
-        let (count, result) = replace_ascii(&map, &content);
+        let (count, result) = replace_ascii(&map, &content).unwrap();
         assert_eq!(
             get_ascii_bytes("changed something toonice somethingelse"),
             result
@@ -554,7 +618,7 @@ mod tests {
     fn replacment_is_bigger_than_original_ascii() {
         let map = create_map();
         let content = get_ascii_bytes("small");
-        let (count, result) = replace_ascii(&map, &content);
+        let (count, result) = replace_ascii(&map, &content).unwrap();
         assert_eq!(get_ascii_bytes("this is bigger than the original"), result);
         assert_eq!(count, 1);
     }
@@ -565,7 +629,7 @@ mod tests {
         let content = get_ascii_bytes(
             "1 string m_Localized = \"Chinese (中文)\"\n0 MetadataCollection m_Metadata\n1 string m_Localized = \"Russian (Русский)\"\n0 MetadataCollection m_Metadata",
         );
-        let (count, result) = replace_ascii(&map, &content);
+        let (count, result) = replace_ascii(&map, &content).unwrap();
         assert_eq!(content, result);
         assert_eq!(count, 0);
     }
@@ -579,7 +643,7 @@ mod tests {
         let content = [prefix.clone(), content].concat();
         let expected = [prefix, expected].concat();
 
-        let (count, result) = replace_ascii(&map, &content);
+        let (count, result) = replace_ascii(&map, &content).unwrap();
         assert_eq!(expected, result);
         assert_eq!(count, 1);
     }
@@ -593,7 +657,7 @@ mod tests {
         let content = [content, prefix.clone()].concat();
         let expected = [expected, prefix].concat();
 
-        let (count, result) = replace_ascii(&map, &content);
+        let (count, result) = replace_ascii(&map, &content).unwrap();
         assert_eq!(expected, result);
         assert_eq!(count, 1);
     }
@@ -628,11 +692,497 @@ mod tests {
         ]
         .concat();
 
-        let (count, result) = replace_ascii(&map, &content);
+        let (count, result) = replace_ascii(&map, &content).unwrap();
         assert_eq!(expected, result);
         assert_eq!(count, 3);
     }
 
+    #[test]
+    fn replaces_non_ascii_words_be() {
+        let map = create_non_ascii_map();
+        let content = get_be_16_bytes("Chinese (中文), русский, Café");
+        let (count, result) = replace_be_16(&map, &content).unwrap();
+        assert_eq!(
+            get_be_16_bytes("Chinese (汉语), российский, Bistro"),
+            result
+        );
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn replaces_surrogate_pairs_be() {
+        let map = create_non_ascii_map();
+        let content = get_be_16_bytes("first 𝒜𝒷𝒸 𠀀");
+        let (count, result) = replace_be_16(&map, &content).unwrap();
+        assert_eq!(get_be_16_bytes("changed 𝔄𝔟𝔠 𠀀"), result);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn keeps_unpaired_surrogates_be() {
+        let map = create_non_ascii_map();
+        let content = [
+            get_be_16_bytes("first "),
+            vec![0xD8, 0x00],
+            get_be_16_bytes("first "),
+            vec![0xDC, 0x00],
+        ]
+        .concat();
+        let expected = [
+            get_be_16_bytes("changed "),
+            vec![0xD8, 0x00],
+            get_be_16_bytes("changed "),
+            vec![0xDC, 0x00],
+        ]
+        .concat();
+        let (count, result) = replace_be_16(&map, &content).unwrap();
+        assert_eq!(expected, result);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn replaces_text_le_32() {
+        let map = create_map();
+        let content = get_le_32_bytes("lorem pondem first");
+        let (count, result) = replace_le_32(&map, &content).unwrap();
+        assert_eq!(get_le_32_bytes("lorem pondem changed"), result);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn works_with_stoppers_le_32() {
+        let map = create_map();
+        let content = get_le_32_bytes("lorem pondem first.");
+        let (count, result) = replace_le_32(&map, &content).unwrap();
+        assert_eq!(get_le_32_bytes("lorem pondem changed."), result);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn change_more_words_le_32() {
+        let map = create_map();
+        let content = get_le_32_bytes("first and another.");
+        let (count, result) = replace_le_32(&map, &content).unwrap();
+        assert_eq!(get_le_32_bytes("changed and something."), result);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn correctly_works_with_plural_le_32() {
+        let map = create_map();
+        let content = get_le_32_bytes("first and another, anothers.");
+        let (count, result) = replace_le_32(&map, &content).unwrap();
+        assert_eq!(
+            get_le_32_bytes("changed and something, somethingelse."),
+            result
+        );
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn new_lines_work_le_32() {
+        let map = create_map();
+        let content = get_le_32_bytes("\nfirst and \nAnother, \nANOTHERS.");
+        let (count, result) = replace_le_32(&map, &content).unwrap();
+        assert_eq!(
+            get_le_32_bytes("\nchanged and \nSomething, \nSOMETHINGELSE."),
+            result
+        );
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn capilization_le_32() {
+        let map = create_map();
+        let content = get_le_32_bytes("First Another Capital Anothers");
+        let (_, result) = replace_le_32(&map, &content).unwrap();
+        assert_eq!(
+            get_le_32_bytes("Changed Something Toonice Somethingelse"),
+            result
+        );
+    }
+
+    #[test]
+    fn uppercase_le_32() {
+        let map = create_map();
+        let content = get_le_32_bytes("FIRST ANOTHER CAPITAL ANOTHERS");
+        let (_, result) = replace_le_32(&map, &content).unwrap();
+        assert_eq!(
+            get_le_32_bytes("CHANGED SOMETHING TOONICE SOMETHINGELSE"),
+            result
+        );
+    }
+
+    #[test]
+    fn lowercase_le_32() {
+        let map = create_map();
+        let content = get_le_32_bytes("first another capital anothers");
+        let (count, result) = replace_le_32(&map, &content).unwrap();
+        assert_eq!(
+            get_le_32_bytes("changed something toonice somethingelse"),
+            result
+        );
+        assert_eq!(count, 4);
+    }
+
+    #[test]
+    fn replacment_is_bigger_than_original_le_32() {
+        let map = create_map();
+        let content = get_le_32_bytes("small");
+        let (count, result) = replace_le_32(&map, &content).unwrap();
+        assert_eq!(get_le_32_bytes("this is bigger than the original"), result);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn replace_asian_and_russian_chars_le_32() {
+        let map = create_map();
+        let content = get_le_32_bytes(
+            "1 string m_Localized = \"Chinese (中文)\"\n0 MetadataCollection m_Metadata\n1 string m_Localized = \"Russian (Русский)\"\n0 MetadataCollection m_Metadata",
+        );
+        let (count, result) = replace_le_32(&map, &content).unwrap();
+        assert_eq!(content, result);
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn add_bytes_in_beggining_le_32() {
+        let map = create_map();
+        let content = get_le_32_bytes("small");
+        let expected = get_le_32_bytes("this is bigger than the original");
+        let prefix: Vec<u8> = vec![22, 1, 255];
+        let content = [prefix.clone(), content].concat();
+        let expected = [prefix, expected].concat();
+
+        let (count, result) = replace_le_32(&map, &content).unwrap();
+        assert_eq!(expected, result);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn add_bytes_to_the_end_le_32() {
+        let map = create_map();
+        let content = get_le_32_bytes("small");
+        let expected = get_le_32_bytes("this is bigger than the original");
+        let prefix: Vec<u8> = vec![22, 1, 255];
+        let content = [content, prefix.clone()].concat();
+        let expected = [expected, prefix].concat();
+
+        let (count, result) = replace_le_32(&map, &content).unwrap();
+        assert_eq!(expected, result);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn add_bytes_in_the_mid_le_32() {
+        let map = create_map();
+        let bytes1 = vec![11, 9];
+        let bytes2 = vec![2];
+        let bytes3 = vec![255, 249];
+        let bytes4 = vec![10];
+
+        let content1 = get_le_32_bytes("\nfirst");
+        let content2 = get_le_32_bytes(" and \nAnother,");
+        let content3 = get_le_32_bytes(" \nANOTHERS.");
+        let content = [
+            bytes1.clone(),
+            content1,
+            bytes2.clone(),
+            content2,
+            bytes3.clone(),
+            content3,
+            bytes4.clone(),
+        ]
+        .concat();
+
+        let expected1 = get_le_32_bytes("\nchanged");
+        let expected2 = get_le_32_bytes(" and \nSomething,");
+        let expected3 = get_le_32_bytes(" \nSOMETHINGELSE.");
+        let expected = [
+            bytes1, expected1, bytes2, expected2, bytes3, expected3, bytes4,
+        ]
+        .concat();
+
+        let (count, result) = replace_le_32(&map, &content).unwrap();
+        assert_eq!(expected, result);
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn replaces_text_be_32() {
+        let map = create_map();
+        let content = get_be_32_bytes("lorem pondem first");
+        let (count, result) = replace_be_32(&map, &content).unwrap();
+        assert_eq!(get_be_32_bytes("lorem pondem changed"), result);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn works_with_stoppers_be_32() {
+        let map = create_map();
+        let content = get_be_32_bytes("lorem pondem first.");
+        let (count, result) = replace_be_32(&map, &content).unwrap();
+        assert_eq!(get_be_32_bytes("lorem pondem changed."), result);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn change_more_words_be_32() {
+        let map = create_map();
+        let content = get_be_32_bytes("first and another.");
+        let (count, result) = replace_be_32(&map, &content).unwrap();
+        assert_eq!(get_be_32_bytes("changed and something."), result);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn correctly_works_with_plural_be_32() {
+        let map = create_map();
+        let content = get_be_32_bytes("first and another, anothers.");
+        let (count, result) = replace_be_32(&map, &content).unwrap();
+        assert_eq!(
+            get_be_32_bytes("changed and something, somethingelse."),
+            result
+        );
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn new_lines_work_be_32() {
+        let map = create_map();
+        let content = get_be_32_bytes("\nfirst and \nAnother, \nANOTHERS.");
+        let (count, result) = replace_be_32(&map, &content).unwrap();
+        assert_eq!(
+            get_be_32_bytes("\nchanged and \nSomething, \nSOMETHINGELSE."),
+            result
+        );
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn capilization_be_32() {
+        let map = create_map();
+        let content = get_be_32_bytes("First Another Capital Anothers");
+        let (_, result) = replace_be_32(&map, &content).unwrap();
+        assert_eq!(
+            get_be_32_bytes("Changed Something Toonice Somethingelse"),
+            result
+        );
+    }
+
+    #[test]
+    fn uppercase_be_32() {
+        let map = create_map();
+        let content = get_be_32_bytes("FIRST ANOTHER CAPITAL ANOTHERS");
+        let (_, result) = replace_be_32(&map, &content).unwrap();
+        assert_eq!(
+            get_be_32_bytes("CHANGED SOMETHING TOONICE SOMETHINGELSE"),
+            result
+        );
+    }
+
+    #[test]
+    fn lowercase_be_32() {
+        let map = create_map();
+        let content = get_be_32_bytes("first another capital anothers");
+        let (count, result) = replace_be_32(&map, &content).unwrap();
+        assert_eq!(
+            get_be_32_bytes("changed something toonice somethingelse"),
+            result
+        );
+        assert_eq!(count, 4);
+    }
+
+    #[test]
+    fn replacment_is_bigger_than_original_be_32() {
+        let map = create_map();
+        let content = get_be_32_bytes("small");
+        let (count, result) = replace_be_32(&map, &content).unwrap();
+        assert_eq!(get_be_32_bytes("this is bigger than the original"), result);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn replace_asian_and_russian_chars_be_32() {
+        let map = create_map();
+        let content = get_be_32_bytes(
+            "1 string m_Localized = \"Chinese (中文)\"\n0 MetadataCollection m_Metadata\n1 string m_Localized = \"Russian (Русский)\"\n0 MetadataCollection m_Metadata",
+        );
+        let (count, result) = replace_be_32(&map, &content).unwrap();
+        assert_eq!(content, result);
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn add_bytes_in_beggining_be_32() {
+        let map = create_map();
+        let content = get_be_32_bytes("small");
+        let expected = get_be_32_bytes("this is bigger than the original");
+        let prefix: Vec<u8> = vec![22, 1, 255];
+        let content = [prefix.clone(), content].concat();
+        let expected = [prefix, expected].concat();
+
+        let (count, result) = replace_be_32(&map, &content).unwrap();
+        assert_eq!(expected, result);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn add_bytes_to_the_end_be_32() {
+        let map = create_map();
+        let content = get_be_32_bytes("small");
+        let expected = get_be_32_bytes("this is bigger than the original");
+        let prefix: Vec<u8> = vec![22, 1, 255];
+        let content = [content, prefix.clone()].concat();
+        let expected = [expected, prefix].concat();
+
+        let (count, result) = replace_be_32(&map, &content).unwrap();
+        assert_eq!(expected, result);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn add_bytes_in_the_mid_be_32() {
+        let map = create_map();
+        let bytes1 = vec![11, 9];
+        let bytes2 = vec![2];
+        let bytes3 = vec![255, 249];
+        let bytes4 = vec![10];
+
+        let content1 = get_be_32_bytes("\nfirst");
+        let content2 = get_be_32_bytes(" and \nAnother,");
+        let content3 = get_be_32_bytes(" \nANOTHERS.");
+        let content = [
+            bytes1.clone(),
+            content1,
+            bytes2.clone(),
+            content2,
+            bytes3.clone(),
+            content3,
+            bytes4.clone(),
+        ]
+        .concat();
+
+        let expected1 = get_be_32_bytes("\nchanged");
+        let expected2 = get_be_32_bytes(" and \nSomething,");
+        let expected3 = get_be_32_bytes(" \nSOMETHINGELSE.");
+        let expected = [
+            bytes1, expected1, bytes2, expected2, bytes3, expected3, bytes4,
+        ]
+        .concat();
+
+        let (count, result) = replace_be_32(&map, &content).unwrap();
+        assert_eq!(expected, result);
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn replaces_non_ascii_words_le_32() {
+        let map = create_non_ascii_map();
+        let content = get_le_32_bytes("Chinese (中文), русский, Café and 𝒜𝒷𝒸");
+        let (count, result) = replace_le_32(&map, &content).unwrap();
+        assert_eq!(
+            get_le_32_bytes("Chinese (汉语), российский, Bistro and 𝔄𝔟𝔠"),
+            result
+        );
+        assert_eq!(count, 4);
+    }
+
+    #[test]
+    fn replaces_non_ascii_words_be_32() {
+        let map = create_non_ascii_map();
+        let content = get_be_32_bytes("Chinese (中文), русский, Café and 𝒜𝒷𝒸");
+        let (count, result) = replace_be_32(&map, &content).unwrap();
+        assert_eq!(
+            get_be_32_bytes("Chinese (汉语), российский, Bistro and 𝔄𝔟𝔠"),
+            result
+        );
+        assert_eq!(count, 4);
+    }
+
+    #[test]
+    fn auto_keeps_bom() {
+        let map = create_map();
+        let content = [vec![0xFF, 0xFE], get_le_16_bytes("first and another.")].concat();
+        let (encoding, count, result) = replace_auto(&map, &content).unwrap();
+        assert_eq!(Encoding::Utf16Le, encoding);
+        assert_eq!(
+            [vec![0xFF, 0xFE], get_le_16_bytes("changed and something.")].concat(),
+            result
+        );
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn auto_without_bom() {
+        let map = create_map();
+        let cases = [
+            (Encoding::Utf8, "first and another.".as_bytes().to_vec()),
+            (Encoding::Utf16Le, get_le_16_bytes("first and another.")),
+            (Encoding::Utf16Be, get_be_16_bytes("first and another.")),
+            (Encoding::Utf32Le, get_le_32_bytes("first and another.")),
+            (Encoding::Utf32Be, get_be_32_bytes("first and another.")),
+        ];
+
+        for (expected, content) in cases {
+            let (encoding, count, result) = replace_auto(&map, &content).unwrap();
+            assert_eq!(expected, encoding);
+            assert_eq!(expected.encode("changed and something."), result);
+            assert_eq!(count, 2);
+        }
+    }
+
+    #[test]
+    fn auto_with_utf32_bom() {
+        let map = create_map();
+        let content = [vec![0, 0, 0xFE, 0xFF], get_be_32_bytes("First")].concat();
+        let (encoding, count, result) = replace_auto(&map, &content).unwrap();
+        assert_eq!(Encoding::Utf32Be, encoding);
+        assert_eq!(
+            [vec![0, 0, 0xFE, 0xFF], get_be_32_bytes("Changed")].concat(),
+            result
+        );
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn replace_with_callback_le() {
+        let content = get_le_16_bytes("First and another");
+        let (count, result) = replace_with(&content, Encoding::Utf16Le, |word, ctx| {
+            (word.len() > 3).then(|| ctx.match_case("changed").into())
+        });
+        assert_eq!(get_le_16_bytes("Changed and changed"), result);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn case_sensitive_le() {
+        let mut map = create_map();
+        map.insert("US".into(), "USA".into());
+        map.insert("us".into(), "we".into());
+        let content = get_le_16_bytes("us US Us First first");
+        let replacer = Replacer::builder()
+            .case_sensitive(true)
+            .build(&map)
+            .unwrap();
+        let (count, result) = replacer.replace_le_16(&content);
+        assert_eq!(get_le_16_bytes("we USA Us First changed"), result);
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn digits_start_words() {
+        let map = create_map();
+        let (count, result) = replace_ascii(&map, b"3first 1first first").unwrap();
+        assert_eq!(b"3first 1first changed".to_vec(), result);
+        assert_eq!(count, 1);
+
+        let content = get_le_16_bytes("3first first");
+        let (count, result) = replace_le_16(&map, &content).unwrap();
+        assert_eq!(get_le_16_bytes("3first changed"), result);
+        assert_eq!(count, 1);
+    }
+
     fn create_map() -> HashMap<String, String> {
         let mut map = HashMap::new();
         map.insert("first".into(), "changed".into());
@@ -643,6 +1193,16 @@ mod tests {
         map
     }
 
+    fn create_non_ascii_map() -> HashMap<String, String> {
+        let mut map = create_map();
+        map.insert("中文".into(), "汉语".into());
+        map.insert("русский".into(), "российский".into());
+        map.insert("café".into(), "bistro".into());
+        map.insert("caf".into(), "wrong".into());
+        map.insert("𝒜𝒷𝒸".into(), "𝔄𝔟𝔠".into());
+        map
+    }
+
     fn get_ascii_bytes(text: &str) -> Vec<u8> {
         text.as_bytes().to_vec()
     }
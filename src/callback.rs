@@ -0,0 +1,139 @@
+use crate::case_matcher::CaseMode;
+use crate::encoding::Encoding;
+use crate::replacer::Edit;
+use crate::scanner;
+use crate::tokenizer::Tokenizer;
+use std::borrow::Cow;
+use std::ops::Range;
+
+/// Where a word sits in the input, handed to replacement callbacks next to the word itself.
+pub struct WordContext<'a> {
+    /// How many words came before this one.
+    pub index: usize,
+    /// The bytes the word takes up in the input.
+    pub range: Range<usize>,
+    pub previous: Option<&'a str>,
+    pub next: Option<&'a str>,
+    word: &'a str,
+    case_mode: CaseMode,
+}
+
+impl WordContext<'_> {
+    /// Gives `replacement` the case of the word, the same way dictionary values get it.
+    pub fn match_case(&self, replacement: &str) -> String {
+        self.case_mode.apply(self.word, replacement)
+    }
+}
+
+pub(crate) fn replace<'r>(
+    encoding: Encoding,
+    tokenizer: &Tokenizer,
+    case_mode: CaseMode,
+    bytes: &[u8],
+    mut replacement: impl FnMut(&str, &WordContext) -> Option<Cow<'r, str>>,
+) -> (u128, Vec<u8>) {
+    let (words, _) = scanner::words(encoding, tokenizer, bytes, true);
+    let mut edits = vec![];
+    for (index, word) in words.iter().enumerate() {
+        let context = WordContext {
+            index,
+            range: word.range.clone(),
+            previous: index
+                .checked_sub(1)
+                .map(|previous| words[previous].text.as_str()),
+            next: words.get(index + 1).map(|next| next.text.as_str()),
+            word: &word.text,
+            case_mode,
+        };
+
+        if let Some(value) = replacement(&word.text, &context) {
+            edits.push(Edit {
+                range: word.range.clone(),
+                key: &word.text,
+                original: word.text.clone(),
+                value: value.into_owned(),
+            });
+        }
+    }
+
+    (
+        edits.len() as u128,
+        scanner::splice(encoding, bytes, &edits, |_, _| {}),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::collections::HashMap;
+
+    use crate::callback::replace;
+    use crate::case_matcher::CaseMode;
+    use crate::encoding::Encoding;
+    use crate::tokenizer::Tokenizer;
+    use crate::utils::get_be_16_bytes;
+
+    #[test]
+    fn replaces_from_callback() {
+        let snapshot: HashMap<&str, &str> = HashMap::from([("first", "changed")]);
+        let (count, result) = replace(
+            Encoding::Utf8,
+            &Tokenizer::new(),
+            CaseMode::Match,
+            "First and first, another".as_bytes(),
+            |word, ctx| {
+                let value = snapshot.get(word.to_lowercase().as_str())?;
+                Some(Cow::Owned(ctx.match_case(value)))
+            },
+        );
+        assert_eq!(
+            "Changed and changed, another",
+            String::from_utf8(result).unwrap()
+        );
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn context_has_position_and_neighbours() {
+        let content = get_be_16_bytes("one two, three");
+        let mut seen = vec![];
+        let (count, result) = replace(
+            Encoding::Utf16Be,
+            &Tokenizer::new(),
+            CaseMode::Match,
+            &content,
+            |word, ctx| {
+                seen.push((
+                    word.to_string(),
+                    ctx.index,
+                    ctx.range.clone(),
+                    ctx.previous.map(String::from),
+                    ctx.next.map(String::from),
+                ));
+                (ctx.previous == Some("one")).then_some(Cow::Borrowed("2"))
+            },
+        );
+        assert_eq!(get_be_16_bytes("one 2, three"), result);
+        assert_eq!(count, 1);
+        assert_eq!(
+            vec![
+                ("one".to_string(), 0, 0..6, None, Some("two".to_string())),
+                (
+                    "two".to_string(),
+                    1,
+                    8..14,
+                    Some("one".to_string()),
+                    Some("three".to_string())
+                ),
+                (
+                    "three".to_string(),
+                    2,
+                    18..28,
+                    Some("two".to_string()),
+                    None
+                ),
+            ],
+            seen
+        );
+    }
+}
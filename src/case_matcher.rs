@@ -1,37 +1,200 @@
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CaseMode {
+    #[default]
+    Match,
+    AsWritten,
+}
+
 enum Case {
     Capitalized,
     Lowercase,
     Uppercase,
+    Uncased,
+    Camel,
+    Pascal,
+    Mixed,
+}
+
+impl CaseMode {
+    pub(crate) fn apply(self, first: &str, second: &str) -> String {
+        match self {
+            CaseMode::Match => match_case(first, second),
+            CaseMode::AsWritten => second.to_string(),
+        }
+    }
 }
 
 pub fn match_case(first: &str, second: &str) -> String {
     let case = find_case(first);
+    // A replacement with humps of its own, like "iPhone" or "MacBook", only has its first letter
+    // changed to match a lowercase or capitalized word.
+    let humps = matches!(find_case(second), Case::Camel | Case::Pascal);
     match case {
-        Case::Lowercase => second.to_ascii_lowercase(),
-        Case::Uppercase => second.to_ascii_uppercase(),
-        Case::Capitalized => [
-            second[0..1].to_ascii_uppercase(),
-            second[1..].to_ascii_lowercase(),
-        ]
-        .concat(),
+        Case::Lowercase if humps => lowercase_first(second),
+        Case::Capitalized if humps => capitalize_first(second),
+        Case::Lowercase => second.to_lowercase(),
+        Case::Uppercase => second.to_uppercase(),
+        Case::Capitalized => capitalize(second),
+        Case::Uncased => second.to_string(),
+        Case::Camel | Case::Pascal => {
+            let segments = segments(second);
+            if segments.len() > 1 {
+                segments
+                    .iter()
+                    .enumerate()
+                    .map(|(index, segment)| match case {
+                        Case::Camel if index == 0 => segment.to_lowercase(),
+                        _ => capitalize(segment),
+                    })
+                    .collect()
+            } else if let Some(mapped) = map_each_char(first, second) {
+                mapped
+            } else if matches!(case, Case::Camel) {
+                second.to_lowercase()
+            } else {
+                capitalize(second)
+            }
+        }
+        Case::Mixed => map_each_char(first, second).unwrap_or_else(|| second.to_string()),
     }
 }
 
 fn find_case(string: &str) -> Case {
-    let mut chars = string.chars();
-    if string.is_empty() || chars.next().unwrap().is_ascii_lowercase() {
+    let cased: Vec<char> = string.chars().filter(|&c| is_cased(c)).collect();
+    let Some(&first) = cased.first() else {
+        return Case::Uncased;
+    };
+
+    if cased.iter().all(|c| c.is_lowercase()) {
         return Case::Lowercase;
     }
 
-    match chars.find(|b| b.is_ascii_lowercase()) {
-        Some(_) => Case::Capitalized,
-        None => Case::Uppercase,
+    if cased.iter().all(|c| c.is_uppercase()) {
+        return Case::Uppercase;
+    }
+
+    if cased[1..].iter().all(|c| c.is_lowercase()) {
+        return Case::Capitalized;
+    }
+
+    // Every capital has to start a hump of lowercase letters, as in "iPhone" or "YouTube".
+    let humps = cased
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| !c.is_lowercase())
+        .all(|(index, _)| cased.get(index + 1).is_some_and(|c| c.is_lowercase()));
+
+    match (humps, first.is_lowercase()) {
+        (true, true) => Case::Camel,
+        (true, false) => Case::Pascal,
+        (false, _) => Case::Mixed,
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => to_titlecase(first) + &chars.as_str().to_lowercase(),
+        None => String::new(),
+    }
+}
+
+fn capitalize_first(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => to_titlecase(first) + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+fn lowercase_first(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+// Splits on spaces, '_' and '-', and where a lowercase letter is followed by a capital.
+fn segments(text: &str) -> Vec<String> {
+    let mut segments = vec![];
+    let mut current = String::new();
+    let mut after_lowercase = false;
+    for c in text.chars() {
+        let separator = c.is_whitespace() || c == '_' || c == '-';
+        if (separator || (after_lowercase && !c.is_lowercase() && is_cased(c)))
+            && !current.is_empty()
+        {
+            segments.push(std::mem::take(&mut current));
+        }
+
+        if !separator {
+            current.push(c);
+        }
+
+        after_lowercase = c.is_lowercase();
+    }
+
+    if !current.is_empty() {
+        segments.push(current);
+    }
+
+    segments
+}
+
+fn map_each_char(first: &str, second: &str) -> Option<String> {
+    if first.chars().count() != second.chars().count() {
+        return None;
+    }
+
+    let mapped = first
+        .chars()
+        .zip(second.chars())
+        .map(|(from, to)| {
+            if from.is_lowercase() {
+                to.to_lowercase().collect()
+            } else if is_cased(from) {
+                to.to_uppercase().collect()
+            } else {
+                to.to_string()
+            }
+        })
+        .collect();
+
+    Some(mapped)
+}
+
+fn is_cased(c: char) -> bool {
+    c.is_lowercase() || c.is_uppercase() || is_titlecase(c)
+}
+
+// Titlecase letters like 'ǅ' are neither uppercase nor lowercase, but still have a lowercase form.
+fn is_titlecase(c: char) -> bool {
+    !c.is_lowercase() && !c.is_uppercase() && !c.to_lowercase().eq([c])
+}
+
+// The standard library has no titlecase mapping, so the letters where it differs from the
+// uppercase one are listed here.
+fn to_titlecase(c: char) -> String {
+    match c {
+        'Ǆ' | 'ǅ' | 'ǆ' => "ǅ".to_string(),
+        'Ǉ' | 'ǈ' | 'ǉ' => "ǈ".to_string(),
+        'Ǌ' | 'ǋ' | 'ǌ' => "ǋ".to_string(),
+        'Ǳ' | 'ǲ' | 'ǳ' => "ǲ".to_string(),
+        'ß' => "Ss".to_string(),
+        'ﬀ' => "Ff".to_string(),
+        'ﬁ' => "Fi".to_string(),
+        'ﬂ' => "Fl".to_string(),
+        'ﬃ' => "Ffi".to_string(),
+        'ﬄ' => "Ffl".to_string(),
+        'ﬅ' | 'ﬆ' => "St".to_string(),
+        _ => c.to_uppercase().collect(),
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::case_matcher::match_case;
+    use crate::case_matcher::{CaseMode, match_case};
 
     #[test]
     fn return_lowercase() {
@@ -59,10 +222,118 @@ mod test {
         assert_eq!("UPPER", match_case("S", "upper"));
     }
 
+    #[test]
+    fn works_with_empty_replacement() {
+        assert_eq!("", match_case("This", ""));
+        assert_eq!("", match_case("UPPER", ""));
+        assert_eq!("", match_case("", ""));
+    }
+
+    #[test]
+    fn capitalizes_replacement_starting_with_non_ascii() {
+        assert_eq!("Émile", match_case("Emil", "émile"));
+        assert_eq!("Ça", match_case("This", "ça"));
+    }
+
+    #[test]
+    fn return_uppercase_non_ascii() {
+        assert_eq!("МИР", match_case("ПРИВЕТ", "мир"));
+        assert_eq!("ÖL", match_case("ÄRGER", "öl"));
+    }
+
+    #[test]
+    fn return_capitalized_non_ascii() {
+        assert_eq!("Ödem", match_case("Ärger", "ödem"));
+        assert_eq!("Мир", match_case("Привет", "МИР"));
+    }
+
+    #[test]
+    fn return_lowercase_non_ascii() {
+        assert_eq!("οδος", match_case("λόγος", "ΟΔΟΣ"));
+        assert_eq!("мир", match_case("привет", "Мир"));
+    }
+
+    #[test]
+    fn uppercase_expands_to_multiple_chars() {
+        assert_eq!("STRASSE", match_case("UPPER", "straße"));
+        assert_eq!("Ssa", match_case("This", "ßa"));
+        assert_eq!("Fine", match_case("This", "ﬁne"));
+    }
+
+    #[test]
+    fn titlecase_digraphs() {
+        assert_eq!("ǅep", match_case("ǅungla", "ǆep"));
+        assert_eq!("ǅep", match_case("Test", "Ǆep"));
+        assert_eq!("ǄEP", match_case("ǄUNGLA", "ǆep"));
+        assert_eq!("ǆep", match_case("ǆungla", "ǅep"));
+        assert_eq!("Ljubav", match_case("ǈ", "ljubav"));
+    }
+
+    #[test]
+    fn uncased_words_keep_replacement() {
+        assert_eq!("Chinese", match_case("中文", "Chinese"));
+        assert_eq!("Mixed Case", match_case("123", "Mixed Case"));
+    }
+
+    #[test]
+    fn case_found_from_first_cased_letter() {
+        assert_eq!("abc", match_case("2fa", "ABC"));
+        assert_eq!("ABC", match_case("2FA", "abc"));
+    }
+
     #[test]
     fn convert_text_to_correct_case() {
         assert_eq!("lower", match_case("lower", "LOWER"));
         assert_eq!("UPPER", match_case("UPPER", "upper"));
         assert_eq!("Cap", match_case("Capitalized", "cAP"));
     }
+
+    #[test]
+    fn camel_case_from_segments() {
+        assert_eq!("smartPhone", match_case("iPhone", "smart phone"));
+        assert_eq!("fetchAccount", match_case("getUser", "fetch_account"));
+        assert_eq!("fetchAccount", match_case("getUser", "FetchAccount"));
+    }
+
+    #[test]
+    fn pascal_case_from_segments() {
+        assert_eq!("SmartPhone", match_case("YouTube", "smart phone"));
+        assert_eq!("FetchAccount", match_case("GetUser", "fetch-account"));
+    }
+
+    #[test]
+    fn maps_each_char_when_lengths_match() {
+        assert_eq!("iPad", match_case("iPod", "ipad"));
+        assert_eq!("tHaT", match_case("tHiS", "that"));
+        assert_eq!("mAC", match_case("iOS", "mac"));
+        assert_eq!("HTMLdoc", match_case("XMLFile", "htmldoc"));
+    }
+
+    #[test]
+    fn single_segment_without_matching_length() {
+        assert_eq!("android", match_case("iPhone", "Android"));
+        assert_eq!("Vimeo", match_case("YouTube", "vimeo"));
+    }
+
+    #[test]
+    fn mixed_case_without_matching_length_is_kept() {
+        assert_eq!("PowerPC", match_case("tHiS", "PowerPC"));
+        assert_eq!("eBay", match_case("iOS", "eBay"));
+    }
+
+    #[test]
+    fn humps_in_replacement_are_kept() {
+        assert_eq!("iPhone", match_case("phone", "iPhone"));
+        assert_eq!("IPhone", match_case("Phone", "iPhone"));
+        assert_eq!("MacBook", match_case("Apple", "MacBook"));
+        assert_eq!("macBook", match_case("apple", "MacBook"));
+        assert_eq!("MACBOOK", match_case("APPLE", "MacBook"));
+    }
+
+    #[test]
+    fn as_written_keeps_replacement() {
+        assert_eq!("iPhone", CaseMode::AsWritten.apply("PHONE", "iPhone"));
+        assert_eq!("iPhone", CaseMode::AsWritten.apply("Phone", "iPhone"));
+        assert_eq!("IPHONE", CaseMode::Match.apply("PHONE", "iPhone"));
+    }
 }
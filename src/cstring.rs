@@ -0,0 +1,159 @@
+use crate::encoding::Encoding;
+use crate::fixed::{self, FixedLength, Violation};
+use crate::replacer::Replacer;
+use std::ops::Range;
+
+/// Picks out the NUL-terminated runs of printable ASCII in a binary, the way `strings(1)` does,
+/// so that only those get replaced. Runs shorter than `min_len` are skipped, as they are
+/// usually bytes of code or data that happen to look like letters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CStrings {
+    min_len: usize,
+    fixed_length: Option<FixedLength>,
+}
+
+impl Default for CStrings {
+    fn default() -> Self {
+        Self {
+            min_len: 4,
+            fixed_length: None,
+        }
+    }
+}
+
+impl CStrings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn min_len(mut self, min_len: usize) -> Self {
+        self.min_len = min_len;
+        self
+    }
+
+    /// Keeps every string at its length, which code pointing into the binary usually needs. The
+    /// string is padded or cut at its end, in front of the terminator.
+    pub fn fixed_length(mut self, fixed_length: FixedLength) -> Self {
+        self.fixed_length = Some(fixed_length);
+        self
+    }
+
+    /// The byte ranges of the strings, without their terminating NUL.
+    pub fn find(&self, bytes: &[u8]) -> Vec<Range<usize>> {
+        let mut strings = vec![];
+        let mut start = 0;
+        for (index, &b) in bytes.iter().enumerate() {
+            if is_printable(b) {
+                continue;
+            }
+
+            if b == 0 && index - start >= self.min_len.max(1) {
+                strings.push(start..index);
+            }
+
+            start = index + 1;
+        }
+
+        strings
+    }
+}
+
+fn is_printable(b: u8) -> bool {
+    b == b'\t' || (b' '..=b'~').contains(&b)
+}
+
+pub(crate) fn replace(
+    replacer: &Replacer,
+    bytes: &[u8],
+    options: &CStrings,
+) -> (u128, Vec<u8>, Vec<Violation>) {
+    let mut count = 0;
+    let mut new_bytes = Vec::with_capacity(bytes.len());
+    let mut violations = vec![];
+    let mut cursor = 0;
+    for range in options.find(bytes) {
+        new_bytes.extend_from_slice(&bytes[cursor..range.start]);
+        let string = &bytes[range.clone()];
+        let (string_count, new_string) = match &options.fixed_length {
+            Some(fixed_length) => {
+                let (string_count, new_string, string_violations) =
+                    fixed::replace_string(replacer, string, fixed_length);
+                violations.extend(string_violations.into_iter().map(|violation| Violation {
+                    input: violation.input.start + range.start..violation.input.end + range.start,
+                    ..violation
+                }));
+                (string_count, new_string)
+            }
+            None => replacer.replace(Encoding::Ascii, string),
+        };
+
+        count += string_count;
+        new_bytes.extend(new_string);
+        cursor = range.end;
+    }
+
+    new_bytes.extend_from_slice(&bytes[cursor..]);
+    (count, new_bytes, violations)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::cstring::CStrings;
+    use crate::fixed::{FixedLength, Overflow};
+    use crate::replacer::Replacer;
+
+    const BINARY: &[u8] = b"\x7fELF\x02\x01first\x00\x8b\xc3first\xe8\x00Press first key\x00first";
+
+    #[test]
+    fn finds_terminated_printable_runs() {
+        assert_eq!(vec![21..36], CStrings::new().min_len(6).find(BINARY));
+        assert_eq!(vec![6..11, 21..36], CStrings::new().find(BINARY));
+        assert_eq!(
+            vec![0..3, 5..8],
+            CStrings::new().min_len(2).find(b"one\0\0\ttw\0\x01three")
+        );
+    }
+
+    #[test]
+    fn replaces_inside_strings_only() {
+        let replacer =
+            Replacer::new(&HashMap::from([("first".to_string(), "any".to_string())])).unwrap();
+        let (count, result, violations) = replacer.replace_c_strings(BINARY, &CStrings::new());
+        assert_eq!(
+            b"\x7fELF\x02\x01any\x00\x8b\xc3first\xe8\x00Press any key\x00first".as_slice(),
+            result
+        );
+        assert_eq!(count, 2);
+        assert!(violations.is_empty());
+    }
+
+    #[test]
+    fn keeps_lengths_when_asked() {
+        let replacer = Replacer::new(&HashMap::from([
+            ("first".to_string(), "any".to_string()),
+            ("key".to_string(), "button".to_string()),
+        ]))
+        .unwrap();
+        let options = CStrings::new().fixed_length(FixedLength::new().filler('\0'));
+        let (count, result, violations) = replacer.replace_c_strings(BINARY, &options);
+        assert_eq!(BINARY.len(), result.len());
+        assert_eq!(
+            b"\x7fELF\x02\x01any\0\0\x00\x8b\xc3first\xe8\x00Press any key\0\0\x00first".as_slice(),
+            result
+        );
+        assert_eq!(count, 2);
+        assert_eq!(33..36, violations[0].input);
+
+        let options = CStrings::new()
+            .fixed_length(FixedLength::new().filler('\0').overflow(Overflow::Truncate));
+        let (count, result, violations) = replacer.replace_c_strings(BINARY, &options);
+        assert_eq!(
+            b"\x7fELF\x02\x01any\0\0\x00\x8b\xc3first\xe8\x00Press any butto\x00first".as_slice(),
+            result
+        );
+        assert_eq!(count, 3);
+        assert_eq!(Some("butto".to_string()), violations[0].written);
+    }
+}
@@ -0,0 +1,622 @@
+use crate::error::ReplaceError;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::iter::Peekable;
+use std::path::Path;
+use std::str::Chars;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Toml,
+    Csv,
+    Tsv,
+    Po,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Option<Format> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "toml" => Some(Format::Toml),
+            "csv" => Some(Format::Csv),
+            "tsv" | "tab" => Some(Format::Tsv),
+            "po" | "pot" => Some(Format::Po),
+            _ => None,
+        }
+    }
+}
+
+struct ParseError {
+    line: usize,
+    message: String,
+}
+
+/// Loads a dictionary, picking the format from the file extension.
+pub fn load(path: impl AsRef<Path>) -> Result<HashMap<String, String>, ReplaceError> {
+    let path = path.as_ref();
+    let format =
+        Format::from_path(path).ok_or_else(|| ReplaceError::UnsupportedFormat(path.into()))?;
+    load_as(path, format)
+}
+
+pub fn load_as(
+    path: impl AsRef<Path>,
+    format: Format,
+) -> Result<HashMap<String, String>, ReplaceError> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+    let text = text.strip_prefix('\u{FEFF}').unwrap_or(&text);
+    let entries = match format {
+        Format::Json => parse_json(text),
+        Format::Toml => parse_toml(text),
+        Format::Csv => parse_delimited(text, ','),
+        Format::Tsv => parse_delimited(text, '\t'),
+        Format::Po => parse_po(text),
+    };
+
+    entries
+        .map(|entries| entries.into_iter().collect())
+        .map_err(|error| ReplaceError::Dictionary {
+            path: path.into(),
+            line: error.line,
+            message: error.message,
+        })
+}
+
+/// Loads every dictionary in order, later files overriding keys of earlier ones.
+pub fn merge<P: AsRef<Path>>(
+    paths: impl IntoIterator<Item = P>,
+) -> Result<HashMap<String, String>, ReplaceError> {
+    let mut dictionary = HashMap::new();
+    for path in paths {
+        dictionary.extend(load(path)?);
+    }
+
+    Ok(dictionary)
+}
+
+fn parse_json(text: &str) -> Result<Vec<(String, String)>, ParseError> {
+    let entries: BTreeMap<String, String> = serde_json::from_str(text).map_err(|error| {
+        let message = error.to_string();
+        ParseError {
+            line: error.line(),
+            message: match message.split_once(" at line ") {
+                Some((message, _)) => message.to_string(),
+                None => message,
+            },
+        }
+    })?;
+
+    // The map keeps the last of two equal keys, so they are looked for in the text as well.
+    let mut lines = HashMap::new();
+    for (key, line) in json_keys(text) {
+        define(&mut lines, &key, line)?;
+    }
+
+    Ok(entries.into_iter().collect())
+}
+
+// The keys of a flat object of strings, which every other string literal in it is.
+fn json_keys(text: &str) -> Vec<(String, usize)> {
+    let mut keys = vec![];
+    let mut literals = 0;
+    let mut line = 1;
+    let mut chars = text.char_indices();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            '"' => {
+                let mut escaped = false;
+                let end = chars.find(|&(_, c)| {
+                    let closes = c == '"' && !escaped;
+                    escaped = c == '\\' && !escaped;
+                    closes
+                });
+                if let Some((end, _)) = end
+                    && literals % 2 == 0
+                    && let Ok(key) = serde_json::from_str(&text[start..=end])
+                {
+                    keys.push((key, line));
+                }
+
+                literals += 1;
+            }
+            _ => {}
+        }
+    }
+
+    keys
+}
+
+// Tables only group entries, the keys inside them are used as they are. So the same key in two
+// tables is an error, as only one of them could be kept.
+fn parse_toml(text: &str) -> Result<Vec<(String, String)>, ParseError> {
+    let table = toml::de::DeTable::parse(text).map_err(|error| ParseError {
+        line: error.span().map_or(1, |span| line_at(text, span.start)),
+        message: error.message().to_string(),
+    })?;
+
+    let mut entries = vec![];
+    let mut lines = HashMap::new();
+    flatten_toml(text, table.get_ref(), &mut entries, &mut lines)?;
+    Ok(entries)
+}
+
+fn flatten_toml(
+    text: &str,
+    table: &toml::de::DeTable,
+    entries: &mut Vec<(String, String)>,
+    lines: &mut HashMap<String, usize>,
+) -> Result<(), ParseError> {
+    for (key, value) in table {
+        let key = key.get_ref().to_string();
+        let line = line_at(text, value.span().start);
+        match value.get_ref() {
+            toml::de::DeValue::String(value) => {
+                if let Some(first) = lines.insert(key.clone(), line) {
+                    return Err(ParseError {
+                        line: line.max(first),
+                        message: format!("{key:?} is already defined on line {}", line.min(first)),
+                    });
+                }
+
+                entries.push((key, value.to_string()));
+            }
+            toml::de::DeValue::Table(table) => flatten_toml(text, table, entries, lines)?,
+            value => {
+                return Err(ParseError {
+                    line,
+                    message: format!("{key:?} is a {}, expected a string", value.type_str()),
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Remembers the line `key` is on, failing when it was already defined. Tables are walked in
+// key order rather than file order, so the error goes on the later of the two lines.
+fn define(lines: &mut HashMap<String, usize>, key: &str, line: usize) -> Result<(), ParseError> {
+    match lines.insert(key.to_string(), line) {
+        Some(first) => Err(ParseError {
+            line: line.max(first),
+            message: format!("{key:?} is already defined on line {}", line.min(first)),
+        }),
+        None => Ok(()),
+    }
+}
+
+fn line_at(text: &str, offset: usize) -> usize {
+    text[..offset.min(text.len())].matches('\n').count() + 1
+}
+
+fn parse_delimited(text: &str, delimiter: char) -> Result<Vec<(String, String)>, ParseError> {
+    let mut entries = vec![];
+    let mut lines = HashMap::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    while chars.peek().is_some() {
+        let start = line;
+        let record =
+            read_record(&mut chars, delimiter, &mut line).map_err(|message| ParseError {
+                line: start,
+                message,
+            })?;
+
+        match record.as_slice() {
+            [field] if field.trim().is_empty() => {}
+            [key, value] => {
+                define(&mut lines, key, start)?;
+                entries.push((key.clone(), value.clone()));
+            }
+            fields => {
+                return Err(ParseError {
+                    line: start,
+                    message: format!("expected 2 columns, found {}", fields.len()),
+                });
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+// Quoted fields follow RFC 4180, they can hold the delimiter, newlines and "" for a quote.
+fn read_record(
+    chars: &mut Peekable<Chars>,
+    delimiter: char,
+    line: &mut usize,
+) -> Result<Vec<String>, String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    loop {
+        match chars.next() {
+            None if quoted => return Err("quoted field is never closed".to_string()),
+            None => break,
+            Some('"') if quoted => {
+                if chars.next_if_eq(&'"').is_some() {
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            Some('"') if field.is_empty() => quoted = true,
+            Some('\n') if quoted => {
+                *line += 1;
+                field.push('\n');
+            }
+            Some('\n') => {
+                *line += 1;
+                break;
+            }
+            Some('\r') if !quoted && chars.peek() == Some(&'\n') => {}
+            Some(c) if c == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+            Some(c) => field.push(c),
+        }
+    }
+
+    fields.push(field);
+    Ok(fields)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PoField {
+    Context,
+    Id,
+    Plural,
+    Str(usize),
+}
+
+#[derive(Default)]
+struct PoEntry {
+    line: usize,
+    context: Option<String>,
+    id: Option<String>,
+    translation: Option<String>,
+    fuzzy: bool,
+}
+
+impl PoEntry {
+    fn is_empty(&self) -> bool {
+        self.context.is_none() && self.id.is_none() && self.translation.is_none()
+    }
+
+    // The header has an empty msgid, and untranslated entries an empty msgstr. Both are skipped,
+    // as are fuzzy entries, whose translation still needs a review. Lookups ignore msgctxt, so of
+    // a msgid in several contexts the first translation is kept. Within one context, a msgid
+    // that comes up again is an error.
+    fn finish(
+        &mut self,
+        entries: &mut Vec<(String, String)>,
+        lines: &mut HashMap<String, (usize, Option<String>)>,
+    ) -> Result<(), ParseError> {
+        let entry = std::mem::take(self);
+        let (Some(id), Some(translation)) = (entry.id, entry.translation) else {
+            return Ok(());
+        };
+
+        if id.is_empty() || translation.is_empty() || entry.fuzzy {
+            return Ok(());
+        }
+
+        match lines.get(&id) {
+            Some((first, context)) if *context == entry.context => Err(ParseError {
+                line: entry.line,
+                message: format!("msgid {id:?} is already translated on line {first}"),
+            }),
+            Some(_) => Ok(()),
+            None => {
+                lines.insert(id.clone(), (entry.line, entry.context));
+                entries.push((id, translation));
+                Ok(())
+            }
+        }
+    }
+}
+
+fn parse_po(text: &str) -> Result<Vec<(String, String)>, ParseError> {
+    let mut entries = vec![];
+    let mut lines = HashMap::new();
+    let mut entry = PoEntry::default();
+    let mut field = None;
+    for (index, line) in text.lines().enumerate() {
+        let error = |message: String| ParseError {
+            line: index + 1,
+            message,
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        // Comments come before the entry they belong to, so they end the one before.
+        if let Some(comment) = line.strip_prefix('#') {
+            if entry.translation.is_some() {
+                entry.finish(&mut entries, &mut lines)?;
+                field = None;
+            }
+
+            if let Some(flags) = comment.strip_prefix(',')
+                && flags.split(',').any(|flag| flag.trim() == "fuzzy")
+            {
+                entry.fuzzy = true;
+            }
+
+            continue;
+        }
+
+        let (keyword, quoted) = match line.find('"') {
+            Some(0) => (None, line),
+            Some(start) => (Some(line[..start].trim()), &line[start..]),
+            None => return Err(error(format!("expected a quoted string in {line:?}"))),
+        };
+        let value = unquote(quoted).map_err(error)?;
+
+        if let Some(keyword) = keyword {
+            field = Some(match keyword {
+                "msgctxt" => PoField::Context,
+                "msgid" => PoField::Id,
+                "msgid_plural" => PoField::Plural,
+                "msgstr" => PoField::Str(0),
+                _ => match keyword
+                    .strip_prefix("msgstr[")
+                    .and_then(|rest| rest.strip_suffix(']'))
+                    .and_then(|index| index.parse().ok())
+                {
+                    Some(index) => PoField::Str(index),
+                    None => return Err(error(format!("unknown keyword {keyword:?}"))),
+                },
+            });
+
+            if matches!(field, Some(PoField::Context | PoField::Id)) && entry.translation.is_some()
+            {
+                entry.finish(&mut entries, &mut lines)?;
+            }
+
+            if entry.is_empty() {
+                entry.line = index + 1;
+            }
+        }
+
+        match field {
+            Some(PoField::Context) => entry.context.get_or_insert_default().push_str(&value),
+            Some(PoField::Id) => entry.id.get_or_insert_default().push_str(&value),
+            Some(PoField::Str(0)) => entry.translation.get_or_insert_default().push_str(&value),
+            Some(_) => {}
+            None => return Err(error("string without a keyword".to_string())),
+        }
+    }
+
+    entry.finish(&mut entries, &mut lines)?;
+    Ok(entries)
+}
+
+fn unquote(quoted: &str) -> Result<String, String> {
+    let inner = quoted
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .filter(|_| quoted.len() > 1)
+        .ok_or_else(|| format!("unterminated string {quoted:?}"))?;
+
+    let mut value = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some('r') => value.push('\r'),
+            Some(c @ ('"' | '\\')) => value.push(c),
+            Some(c) => return Err(format!("unknown escape \\{c}")),
+            None => return Err(format!("unterminated string {quoted:?}")),
+        }
+    }
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::dictionary::{Format, load, load_as, merge};
+    use crate::error::ReplaceError;
+    use crate::utils::TempDir;
+
+    #[test]
+    fn loads_json() {
+        let dir = TempDir::new("dictionary-loads-json");
+        let path = dir.write(
+            "glossary.json",
+            "{\n  \"first\": \"changed\",\n  \"sign in\": \"log in\"\n}",
+        );
+        let dictionary = load(&path).unwrap();
+        assert_eq!(
+            create_map(&[("first", "changed"), ("sign in", "log in")]),
+            dictionary
+        );
+    }
+
+    #[test]
+    fn loads_toml_with_tables() {
+        let dir = TempDir::new("dictionary-loads-toml-with-tables");
+        let content = "first = \"changed\"\n\n[ui]\n\"sign in\" = \"log in\"\n";
+        let dictionary = load(dir.write("glossary.toml", content)).unwrap();
+        assert_eq!(
+            create_map(&[("first", "changed"), ("sign in", "log in")]),
+            dictionary
+        );
+    }
+
+    #[test]
+    fn loads_csv_and_tsv() {
+        let dir = TempDir::new("dictionary-loads-csv-and-tsv");
+        let content = "first,changed\r\n\r\n\"new, york\",\"the \"\"big\"\" apple\"\n";
+        let dictionary = load(dir.write("glossary.csv", content)).unwrap();
+        let expected = create_map(&[("first", "changed"), ("new, york", "the \"big\" apple")]);
+        assert_eq!(expected, dictionary);
+
+        let dictionary =
+            load(dir.write("glossary.tsv", "first\tchanged\nsign in\tlog in")).unwrap();
+        assert_eq!(
+            create_map(&[("first", "changed"), ("sign in", "log in")]),
+            dictionary
+        );
+    }
+
+    #[test]
+    fn loads_po() {
+        let dir = TempDir::new("dictionary-loads-po");
+        let content = r#"# Glossary
+msgid ""
+msgstr ""
+"Language: de\n"
+
+#: src/main.rs:10
+msgid "first"
+msgstr "erste"
+
+msgctxt "menu"
+msgid "sign "
+"in"
+msgstr "anmelden"
+
+msgctxt "door"
+msgid "sign in"
+msgstr "eintragen"
+
+msgid "untranslated"
+msgstr ""
+
+#, fuzzy, c-format
+msgid "guessed"
+msgstr "geraten"
+
+msgid "file"
+msgid_plural "files"
+msgstr[0] "Datei"
+msgstr[1] "Dateien"
+"#;
+        let dictionary = load(dir.write("glossary.po", content)).unwrap();
+        let expected = create_map(&[
+            ("first", "erste"),
+            ("sign in", "anmelden"),
+            ("file", "Datei"),
+        ]);
+        assert_eq!(expected, dictionary);
+    }
+
+    #[test]
+    fn duplicate_keys_are_errors() {
+        let dir = TempDir::new("dictionary-duplicate-keys-are-errors");
+        let po = "msgctxt \"menu\"\nmsgid \"open\"\nmsgstr \"öffnen\"\n\n\
+                  msgctxt \"menu\"\nmsgid \"open\"\nmsgstr \"offen\"\n";
+        let toml = "first = \"changed\"\n\n[ui]\nfirst = \"other\"\n";
+        let spaced = "a = \"x\"\n[ui]\nb = \"y\"\n\n\na = \"z\"\n";
+        let dotted = "[ui]\n\"sign in\" = \"log in\"\n\n[menu]\nsub.\"sign in\" = \"enter\"\n";
+        let json = "{\n  \"first\": \"changed\",\n  \"fir\\u0073t\": \"other\"\n}";
+        for (name, content, expected_line) in [
+            ("twice.json", json, 3),
+            (
+                "twice.csv",
+                "first,changed\n\"sign, in\",log in\nfirst,other\n",
+                3,
+            ),
+            ("twice.tsv", "first\tchanged\nfirst\tother\n", 2),
+            ("twice.po", po, 5),
+            ("twice.toml", toml, 4),
+            ("spaced.toml", spaced, 6),
+            ("dotted.toml", dotted, 5),
+        ] {
+            match load(dir.write(name, content)) {
+                Err(ReplaceError::Dictionary { line, message, .. }) => {
+                    assert_eq!(expected_line, line, "{name}: {message}");
+                    assert!(message.contains("already"), "{name}: {message}");
+                }
+                other => panic!("{name}: expected a dictionary error, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn merges_with_later_files_winning() {
+        let dir = TempDir::new("dictionary-merges-with-later-files-winning");
+        let first = dir.write(
+            "merge.json",
+            "{\"first\": \"changed\", \"another\": \"something\"}",
+        );
+        let second = dir.write("merge.csv", "first,replaced");
+        let dictionary = merge([first, second]).unwrap();
+        let expected = create_map(&[("first", "replaced"), ("another", "something")]);
+        assert_eq!(expected, dictionary);
+    }
+
+    #[test]
+    fn errors_carry_file_and_line() {
+        let dir = TempDir::new("dictionary-errors-carry-file-and-line");
+        let cases = [
+            (
+                "bad.json",
+                "{\n  \"first\": \"changed\",\n  \"second\": 2\n}",
+                3,
+            ),
+            ("bad.toml", "first = \"changed\"\nsecond = 2\n", 2),
+            ("nested.toml", "a = \"x\"\n[ui]\nb = \"y\"\n\n\nc = 3", 6),
+            ("bad.csv", "first,changed\nsecond\n", 2),
+            ("bad.tsv", "first\tchanged\n\"second\tvalue\n", 2),
+            (
+                "bad.po",
+                "msgid \"first\"\nmsgstr \"erste\"\nmsgfoo \"x\"\n",
+                3,
+            ),
+        ];
+        for (name, content, expected_line) in cases {
+            let path = dir.write(name, content);
+            match load(&path) {
+                Err(ReplaceError::Dictionary {
+                    path: error_path,
+                    line,
+                    ..
+                }) => {
+                    assert_eq!(path, error_path);
+                    assert_eq!(expected_line, line, "{name}");
+                }
+                other => panic!("{name}: expected a dictionary error, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_extension() {
+        let dir = TempDir::new("dictionary-unknown-extension");
+        let path = dir.write("glossary.txt", "first,changed");
+        assert!(matches!(
+            load(&path),
+            Err(ReplaceError::UnsupportedFormat(_))
+        ));
+        assert_eq!(1, load_as(&path, Format::Csv).unwrap().len());
+    }
+
+    #[test]
+    fn missing_file() {
+        assert!(matches!(
+            load("missing/glossary.json"),
+            Err(ReplaceError::Io(_))
+        ));
+    }
+
+    fn create_map(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+}
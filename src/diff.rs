@@ -0,0 +1,185 @@
+use crate::encoding::Encoding;
+use similar::TextDiff;
+use std::path::Path;
+
+const CONTEXT_LINES: usize = 3;
+
+/// A unified diff between two versions of the file at `path`, with "a/" and "b/" headers so
+/// `git apply` and `patch -p1` take it. 8-bit files are diffed byte for byte, BOM included, and
+/// ones that are not valid UTF-8 only get a "Binary files differ" line, as the diff is a
+/// string. UTF-16 and UTF-32 files are decoded to show as text, and their diff is marked as
+/// one that does not apply.
+pub(crate) fn unified(encoding: Encoding, path: &Path, old: &[u8], new: &[u8]) -> String {
+    let path = path.to_string_lossy();
+    let path = path.strip_prefix("./").unwrap_or(&path);
+    let (old_path, new_path) = (format!("a/{path}"), format!("b/{path}"));
+    let (old, new, preamble) = match encoding {
+        Encoding::Ascii | Encoding::Utf8 => {
+            match (
+                String::from_utf8(old.to_vec()),
+                String::from_utf8(new.to_vec()),
+            ) {
+                (Ok(old), Ok(new)) => (old, new, String::new()),
+                _ if old == new => return String::new(),
+                _ => return format!("Binary files {old_path} and {new_path} differ\n"),
+            }
+        }
+        _ => (
+            decode(encoding, old),
+            decode(encoding, new),
+            format!("Decoded from {encoding:?} for reading, this diff does not apply.\n"),
+        ),
+    };
+
+    let diff = TextDiff::from_lines(&old, &new)
+        .unified_diff()
+        .context_radius(CONTEXT_LINES)
+        .header(&old_path, &new_path)
+        .to_string();
+    if diff.is_empty() {
+        return diff;
+    }
+
+    preamble + &diff
+}
+
+fn decode(encoding: Encoding, bytes: &[u8]) -> String {
+    let bytes = bytes.strip_prefix(encoding.bom()).unwrap_or(bytes);
+    encoding.decode_lossy(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::diff::unified;
+    use crate::encoding::Encoding;
+    use crate::utils::get_le_16_bytes;
+
+    #[test]
+    fn diff_of_changed_lines() {
+        let old = "one\ntwo\nthree\nfour\nfive\nsix\nseven\nfirst\n";
+        let new = "one\ntwo\nthree\nfour\nfive\nsix\nseven\nchanged\n";
+        let expected = "\
+--- a/notes.txt
++++ b/notes.txt
+@@ -5,4 +5,4 @@
+ five
+ six
+ seven
+-first
++changed
+";
+        let diff = unified(
+            Encoding::Utf8,
+            Path::new("./notes.txt"),
+            old.as_bytes(),
+            new.as_bytes(),
+        );
+        assert_eq!(expected, diff);
+    }
+
+    #[test]
+    fn utf16_is_decoded() {
+        let old = [vec![0xFF, 0xFE], get_le_16_bytes("first\nkeep")].concat();
+        let new = [vec![0xFF, 0xFE], get_le_16_bytes("changed\nkeep")].concat();
+        let expected = "\
+Decoded from Utf16Le for reading, this diff does not apply.
+--- a/wide.txt
++++ b/wide.txt
+@@ -1,2 +1,2 @@
+-first
++changed
+ keep
+\\ No newline at end of file
+";
+        let diff = unified(Encoding::Utf16Le, Path::new("wide.txt"), &old, &new);
+        assert_eq!(expected, diff);
+    }
+
+    #[test]
+    fn bom_is_kept_on_both_sides() {
+        let old = "\u{FEFF}first line\nkeep\nlast first";
+        let new = "\u{FEFF}changed line\nkeep\nlast changed";
+        let diff = unified(
+            Encoding::Utf8,
+            Path::new("bom.txt"),
+            old.as_bytes(),
+            new.as_bytes(),
+        );
+        assert!(diff.starts_with("--- a/bom.txt\n+++ b/bom.txt\n"), "{diff}");
+        assert_eq!(new, apply(old, &diff));
+    }
+
+    #[test]
+    fn invalid_utf8_is_binary() {
+        let diff = unified(
+            Encoding::Ascii,
+            Path::new("latin.txt"),
+            b"caf\xe9",
+            b"caf\xe8",
+        );
+        assert_eq!("Binary files a/latin.txt and b/latin.txt differ\n", diff);
+        let diff = unified(
+            Encoding::Ascii,
+            Path::new("latin.txt"),
+            b"caf\xe9",
+            b"caf\xe9",
+        );
+        assert_eq!("", diff);
+    }
+
+    #[test]
+    fn no_changes() {
+        let diff = unified(Encoding::Ascii, Path::new("same"), b"same", b"same");
+        assert_eq!("", diff);
+    }
+
+    // Applies the hunks of `diff` to `old` the way `patch` does, checking every context line.
+    fn apply(old: &str, diff: &str) -> String {
+        let old: Vec<&str> = old.split_inclusive('\n').collect();
+        let mut new = String::new();
+        let mut line = 0;
+        for hunk in diff.split("\n@@ ").skip(1) {
+            let (range, body) = hunk.split_once('\n').unwrap();
+            let start: usize = range[1..]
+                .split([',', ' '])
+                .next()
+                .unwrap()
+                .parse()
+                .unwrap();
+            while line + 1 < start {
+                new.push_str(old[line]);
+                line += 1;
+            }
+
+            let mut last = "";
+            for text in body.split_inclusive('\n') {
+                let (op, text) = text.split_at(1);
+                match op {
+                    " " | "-" => {
+                        assert_eq!(
+                            old[line].trim_end_matches('\n'),
+                            text.trim_end_matches('\n')
+                        );
+                        if op == " " {
+                            new.push_str(old[line]);
+                        }
+                        line += 1;
+                    }
+                    "+" => new.push_str(text),
+                    // Only an added line carries a newline the file does not have.
+                    "\\" if last == "+" => {
+                        new.pop();
+                    }
+                    _ => {}
+                }
+
+                last = op;
+            }
+        }
+
+        old[line..].iter().for_each(|rest| new.push_str(rest));
+        new
+    }
+}
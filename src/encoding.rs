@@ -0,0 +1,254 @@
+use crate::utils::{get_be_16_bytes, get_be_32_bytes, get_le_16_bytes, get_le_32_bytes};
+use std::str;
+
+const DETECTION_SAMPLE: usize = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    Ascii,
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+pub(crate) enum Unit {
+    Char(char, usize),
+    Invalid,
+    Incomplete,
+}
+
+impl Encoding {
+    /// Picks the encoding from the byte order mark, or from where the null bytes fall when there
+    /// is none. Input without null bytes is UTF-8 when it is valid, and plain 8-bit otherwise.
+    pub fn detect(bytes: &[u8]) -> Encoding {
+        let with_bom = [
+            Encoding::Utf32Le,
+            Encoding::Utf32Be,
+            Encoding::Utf8,
+            Encoding::Utf16Le,
+            Encoding::Utf16Be,
+        ];
+        if let Some(encoding) = with_bom.into_iter().find(|e| bytes.starts_with(e.bom())) {
+            return encoding;
+        }
+
+        let sample = &bytes[..bytes.len().min(DETECTION_SAMPLE)];
+        let mut zeros = [0; 4];
+        let mut totals = [0; 4];
+        for (index, &b) in sample.iter().enumerate() {
+            totals[index % 4] += 1;
+            if b == 0 {
+                zeros[index % 4] += 1;
+            }
+        }
+
+        let ratio = |positions: &[usize]| {
+            let zero: usize = positions.iter().map(|&p| zeros[p]).sum();
+            let total: usize = positions.iter().map(|&p| totals[p]).sum();
+            zero as f64 / total.max(1) as f64
+        };
+
+        let (even, odd) = (ratio(&[0, 2]), ratio(&[1, 3]));
+        if ratio(&[2, 3]) > 0.9 && ratio(&[0]) < 0.1 {
+            Encoding::Utf32Le
+        } else if ratio(&[0, 1]) > 0.9 && ratio(&[3]) < 0.1 {
+            Encoding::Utf32Be
+        } else if odd > 0.2 && even < odd / 4.0 {
+            Encoding::Utf16Le
+        } else if even > 0.2 && odd < even / 4.0 {
+            Encoding::Utf16Be
+        } else if str::from_utf8(bytes).is_ok() {
+            Encoding::Utf8
+        } else {
+            Encoding::Ascii
+        }
+    }
+
+    pub fn bom(self) -> &'static [u8] {
+        match self {
+            Encoding::Ascii => &[],
+            Encoding::Utf8 => &[0xEF, 0xBB, 0xBF],
+            Encoding::Utf16Le => &[0xFF, 0xFE],
+            Encoding::Utf16Be => &[0xFE, 0xFF],
+            Encoding::Utf32Le => &[0xFF, 0xFE, 0x00, 0x00],
+            Encoding::Utf32Be => &[0x00, 0x00, 0xFE, 0xFF],
+        }
+    }
+
+    pub(crate) fn decode(self, bytes: &[u8]) -> Unit {
+        match self {
+            Encoding::Ascii => match bytes.first() {
+                Some(&b) if b.is_ascii() => Unit::Char(char::from(b), 1),
+                Some(_) => Unit::Invalid,
+                None => Unit::Incomplete,
+            },
+            Encoding::Utf8 => decode_utf8(bytes),
+            Encoding::Utf16Le => decode_utf16(bytes, u16::from_le_bytes),
+            Encoding::Utf16Be => decode_utf16(bytes, u16::from_be_bytes),
+            Encoding::Utf32Le => decode_utf32(bytes, u32::from_le_bytes),
+            Encoding::Utf32Be => decode_utf32(bytes, u32::from_be_bytes),
+        }
+    }
+
+    pub(crate) fn decode_str(self, mut bytes: &[u8]) -> Option<String> {
+        let mut text = String::with_capacity(bytes.len());
+        while !bytes.is_empty() {
+            match self.decode(bytes) {
+                Unit::Char(ch, len) => {
+                    text.push(ch);
+                    bytes = &bytes[len..];
+                }
+                Unit::Invalid | Unit::Incomplete => return None,
+            }
+        }
+
+        Some(text)
+    }
+
+    /// Decodes `bytes` with every unit that is not valid text shown as U+FFFD.
+    pub(crate) fn decode_lossy(self, mut bytes: &[u8]) -> String {
+        let mut text = String::with_capacity(bytes.len());
+        while !bytes.is_empty() {
+            match self.decode(bytes) {
+                Unit::Char(ch, len) => {
+                    text.push(ch);
+                    bytes = &bytes[len..];
+                }
+                Unit::Invalid | Unit::Incomplete => {
+                    text.push(char::REPLACEMENT_CHARACTER);
+                    bytes = &bytes[self.unit_len().min(bytes.len())..];
+                }
+            }
+        }
+
+        text
+    }
+
+    pub(crate) fn encode(self, text: &str) -> Vec<u8> {
+        match self {
+            Encoding::Ascii | Encoding::Utf8 => text.as_bytes().to_vec(),
+            Encoding::Utf16Le => get_le_16_bytes(text),
+            Encoding::Utf16Be => get_be_16_bytes(text),
+            Encoding::Utf32Le => get_le_32_bytes(text),
+            Encoding::Utf32Be => get_be_32_bytes(text),
+        }
+    }
+
+    pub(crate) fn unit_len(self) -> usize {
+        match self {
+            Encoding::Ascii | Encoding::Utf8 => 1,
+            Encoding::Utf16Le | Encoding::Utf16Be => 2,
+            Encoding::Utf32Le | Encoding::Utf32Be => 4,
+        }
+    }
+}
+
+fn decode_utf8(bytes: &[u8]) -> Unit {
+    if bytes.is_empty() {
+        return Unit::Incomplete;
+    }
+
+    let head = &bytes[..bytes.len().min(4)];
+    let valid = match str::from_utf8(head) {
+        Ok(text) => text,
+        Err(error) if error.valid_up_to() > 0 => {
+            str::from_utf8(&head[..error.valid_up_to()]).unwrap_or_default()
+        }
+        Err(error) => {
+            return match error.error_len() {
+                Some(_) => Unit::Invalid,
+                None => Unit::Incomplete,
+            };
+        }
+    };
+
+    match valid.chars().next() {
+        Some(ch) => Unit::Char(ch, ch.len_utf8()),
+        None => Unit::Invalid,
+    }
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Unit {
+    let unit = |index: usize| {
+        bytes
+            .get(index..index + 2)
+            .map(|pair| from_bytes([pair[0], pair[1]]))
+    };
+
+    let Some(first) = unit(0) else {
+        return Unit::Incomplete;
+    };
+
+    if !(0xD800..0xDC00).contains(&first) {
+        return match char::from_u32(first as u32) {
+            Some(ch) => Unit::Char(ch, 2),
+            None => Unit::Invalid,
+        };
+    }
+
+    match unit(2) {
+        Some(second) => match char::decode_utf16([first, second]).next() {
+            Some(Ok(ch)) => Unit::Char(ch, 4),
+            _ => Unit::Invalid,
+        },
+        None => Unit::Incomplete,
+    }
+}
+
+fn decode_utf32(bytes: &[u8], from_bytes: fn([u8; 4]) -> u32) -> Unit {
+    match bytes {
+        [first, second, third, fourth, ..] => {
+            match char::from_u32(from_bytes([*first, *second, *third, *fourth])) {
+                Some(ch) => Unit::Char(ch, 4),
+                None => Unit::Invalid,
+            }
+        }
+        _ => Unit::Incomplete,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encoding::Encoding;
+    use crate::utils::{get_be_16_bytes, get_be_32_bytes, get_le_16_bytes, get_le_32_bytes};
+
+    #[test]
+    fn detects_bom() {
+        for encoding in [
+            Encoding::Utf8,
+            Encoding::Utf16Le,
+            Encoding::Utf16Be,
+            Encoding::Utf32Le,
+            Encoding::Utf32Be,
+        ] {
+            let content = [encoding.bom(), &encoding.encode("中文 text")].concat();
+            assert_eq!(encoding, Encoding::detect(&content));
+        }
+    }
+
+    #[test]
+    fn detects_null_byte_patterns() {
+        let text = "lorem pondem first, Русский";
+        assert_eq!(Encoding::Utf16Le, Encoding::detect(&get_le_16_bytes(text)));
+        assert_eq!(Encoding::Utf16Be, Encoding::detect(&get_be_16_bytes(text)));
+        assert_eq!(Encoding::Utf32Le, Encoding::detect(&get_le_32_bytes(text)));
+        assert_eq!(Encoding::Utf32Be, Encoding::detect(&get_be_32_bytes(text)));
+    }
+
+    #[test]
+    fn detects_utf8_without_null_bytes() {
+        assert_eq!(
+            Encoding::Utf8,
+            Encoding::detect("Chinese (中文)".as_bytes())
+        );
+        assert_eq!(Encoding::Utf8, Encoding::detect(b"plain ascii"));
+        assert_eq!(Encoding::Utf8, Encoding::detect(b""));
+    }
+
+    #[test]
+    fn falls_back_to_ascii_for_invalid_utf8() {
+        assert_eq!(Encoding::Ascii, Encoding::detect(&[b'a', 0xFF, b'b', 0xC0]));
+    }
+}
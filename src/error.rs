@@ -0,0 +1,55 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum ReplaceError {
+    InvalidKey(String),
+    Io(io::Error),
+    Dictionary {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    UnsupportedFormat(PathBuf),
+    InvalidPattern(String),
+    InvalidAsset(String),
+}
+
+impl Display for ReplaceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplaceError::InvalidKey(key) => write!(
+                f,
+                "dictionary key {key:?} has to start and end with a word character"
+            ),
+            ReplaceError::Io(error) => write!(f, "{error}"),
+            ReplaceError::Dictionary {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{line}: {message}", path.display()),
+            ReplaceError::UnsupportedFormat(path) => {
+                write!(f, "{} is not a known dictionary format", path.display())
+            }
+            ReplaceError::InvalidPattern(message) => write!(f, "invalid pattern: {message}"),
+            ReplaceError::InvalidAsset(message) => write!(f, "invalid Unity asset: {message}"),
+        }
+    }
+}
+
+impl Error for ReplaceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReplaceError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ReplaceError {
+    fn from(error: io::Error) -> Self {
+        ReplaceError::Io(error)
+    }
+}
@@ -0,0 +1,318 @@
+use crate::diff;
+use crate::encoding::Encoding;
+use crate::error::ReplaceError;
+use crate::replacer::Replacer;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Debug, Default)]
+pub struct FileOptions {
+    encoding: Option<Encoding>,
+    keep_modified_time: bool,
+    backup: bool,
+    dry_run: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileCount {
+    pub path: PathBuf,
+    pub encoding: Encoding,
+    pub count: u128,
+    /// The changes as a unified diff, only filled in on a dry run.
+    pub diff: Option<String>,
+}
+
+/// A file that could not be replaced, while the others were.
+#[derive(Debug)]
+pub struct FileError {
+    pub path: PathBuf,
+    pub error: ReplaceError,
+}
+
+impl FileOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Without an encoding it is detected from the file, and a BOM is kept.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    pub(crate) fn has_encoding(&self) -> bool {
+        self.encoding.is_some()
+    }
+
+    pub fn keep_modified_time(mut self, keep: bool) -> Self {
+        self.keep_modified_time = keep;
+        self
+    }
+
+    /// Leaves a copy of the original next to it, with ".bak" added to its name.
+    pub fn backup(mut self, backup: bool) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    /// Works out the replacements and their diff without writing anything.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+}
+
+/// The new content goes to a temporary file in the same directory, which only replaces the
+/// original once it is fully on disk and has its permissions. A file without any replacements
+/// is left untouched.
+pub(crate) fn replace(
+    replacer: &Replacer,
+    path: &Path,
+    options: &FileOptions,
+) -> Result<FileCount, ReplaceError> {
+    // Writing through a symlink keeps the link in place.
+    let target = fs::canonicalize(path)?;
+    let bytes = fs::read(&target)?;
+    rewrite(replacer, path, &target, &bytes, options)
+}
+
+/// Same as `replace`, for callers that already read the file. It is reported and diffed as
+/// `path`, and written at `target`.
+pub(crate) fn rewrite(
+    replacer: &Replacer,
+    path: &Path,
+    target: &Path,
+    bytes: &[u8],
+    options: &FileOptions,
+) -> Result<FileCount, ReplaceError> {
+    let (encoding, count, new_bytes) = match options.encoding {
+        Some(encoding) => {
+            let (count, new_bytes) = replacer.replace(encoding, bytes);
+            (encoding, count, new_bytes)
+        }
+        None => replacer.replace_auto(bytes),
+    };
+
+    let mut file = FileCount {
+        path: path.to_path_buf(),
+        encoding,
+        count,
+        diff: None,
+    };
+
+    if count == 0 {
+        return Ok(file);
+    }
+
+    if options.dry_run {
+        file.diff = Some(diff::unified(encoding, path, bytes, &new_bytes));
+        return Ok(file);
+    }
+
+    let metadata = fs::metadata(target)?;
+    if options.backup {
+        let backup = with_suffix(target, ".bak");
+        fs::copy(target, &backup)?;
+        File::open(&backup)?.sync_all()?;
+    }
+
+    let (mut temp_file, temp) = create_temp(target)?;
+    let written = (|| {
+        // Set before any content is written, so the new bytes are never readable more widely
+        // than the original file.
+        temp_file.set_permissions(metadata.permissions())?;
+        temp_file.write_all(&new_bytes)?;
+        if options.keep_modified_time {
+            temp_file.set_modified(metadata.modified()?)?;
+        }
+
+        temp_file.sync_all()?;
+        drop(temp_file);
+        fs::rename(&temp, target)
+    })();
+
+    if let Err(error) = written {
+        let _ = fs::remove_file(&temp);
+        return Err(error.into());
+    }
+
+    sync_dir(target);
+    Ok(file)
+}
+
+fn create_temp(path: &Path) -> Result<(File, PathBuf), ReplaceError> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    loop {
+        let counter = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let temp = path.with_file_name(format!(".{name}.{}.{counter}.tmp", process::id()));
+        match OpenOptions::new().write(true).create_new(true).open(&temp) {
+            Ok(file) => return Ok((file, temp)),
+            Err(error) if error.kind() == ErrorKind::AlreadyExists => continue,
+            Err(error) => return Err(error.into()),
+        }
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    name.into()
+}
+
+// The rename itself is only durable once the directory is synced. Not every platform allows
+// opening a directory, and the file is already in place by now, so failures are ignored.
+fn sync_dir(path: &Path) {
+    if let Some(dir) = path.parent()
+        && let Ok(dir) = File::open(dir)
+    {
+        let _ = dir.sync_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    use crate::encoding::Encoding;
+    use crate::error::ReplaceError;
+    use crate::file::FileOptions;
+    use crate::replacer::Replacer;
+    use crate::utils::{TempDir, get_le_16_bytes};
+
+    #[test]
+    fn rewrites_file_without_leftovers() {
+        let dir = TempDir::new("file-rewrite");
+        let path = dir.join("text.txt");
+        fs::write(&path, "first and another").unwrap();
+
+        let replacer = Replacer::new(&create_map()).unwrap();
+        let result = replacer.replace_file(&path, &FileOptions::new()).unwrap();
+        assert_eq!((Encoding::Utf8, 2), (result.encoding, result.count));
+        assert_eq!("changed and something", fs::read_to_string(&path).unwrap());
+        assert_eq!(vec!["text.txt".to_string()], list(&dir));
+    }
+
+    #[test]
+    fn keeps_backup() {
+        let dir = TempDir::new("file-backup");
+        let path = dir.join("text.txt");
+        let content = [vec![0xFF, 0xFE], get_le_16_bytes("First")].concat();
+        fs::write(&path, &content).unwrap();
+
+        let replacer = Replacer::new(&create_map()).unwrap();
+        let options = FileOptions::new().backup(true);
+        let result = replacer.replace_file(&path, &options).unwrap();
+        assert_eq!((Encoding::Utf16Le, 1), (result.encoding, result.count));
+        assert_eq!(content, fs::read(dir.join("text.txt.bak")).unwrap());
+        assert_eq!(
+            [vec![0xFF, 0xFE], get_le_16_bytes("Changed")].concat(),
+            fs::read(&path).unwrap()
+        );
+    }
+
+    #[test]
+    fn keeps_modified_time() {
+        let dir = TempDir::new("file-mtime");
+        let path = dir.join("text.txt");
+        fs::write(&path, "first").unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        let replacer = Replacer::new(&create_map()).unwrap();
+        let options = FileOptions::new()
+            .encoding(Encoding::Ascii)
+            .keep_modified_time(true);
+        replacer.replace_file(&path, &options).unwrap();
+        assert_eq!("changed", fs::read_to_string(&path).unwrap());
+        assert_eq!(modified, fs::metadata(&path).unwrap().modified().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("file-permissions");
+        let replacer = Replacer::new(&create_map()).unwrap();
+        for (name, mode) in [("script.sh", 0o751), ("secret.txt", 0o600)] {
+            let path = dir.join(name);
+            fs::write(&path, "echo first").unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+
+            replacer.replace_file(&path, &FileOptions::new()).unwrap();
+            assert_eq!("echo changed", fs::read_to_string(&path).unwrap());
+            let written = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode, written & 0o777, "{name}");
+        }
+    }
+
+    #[test]
+    fn leaves_unchanged_file_alone() {
+        let dir = TempDir::new("file-unchanged");
+        let path = dir.join("text.txt");
+        fs::write(&path, "nothing here").unwrap();
+
+        let replacer = Replacer::new(&create_map()).unwrap();
+        let options = FileOptions::new().backup(true);
+        assert_eq!(0, replacer.replace_file(&path, &options).unwrap().count);
+        assert_eq!(vec!["text.txt".to_string()], list(&dir));
+    }
+
+    #[test]
+    fn dry_run_only_diffs() {
+        // Tests run from the package root, so this path stays relative like a caller's would.
+        let dir = TempDir::new_in(Path::new("target"), "file-dry-run");
+        let path = dir.join("text.txt");
+        fs::write(&path, "keep\nfirst\n").unwrap();
+
+        let replacer = Replacer::new(&create_map()).unwrap();
+        let options = FileOptions::new().dry_run(true).backup(true);
+        let result = replacer.replace_file(&path, &options).unwrap();
+        assert_eq!(1, result.count);
+        let diff = result.diff.unwrap();
+        let name = path.display();
+        let expected =
+            format!("--- a/{name}\n+++ b/{name}\n@@ -1,2 +1,2 @@\n keep\n-first\n+changed\n");
+        assert_eq!(expected, diff);
+        assert_eq!("keep\nfirst\n", fs::read_to_string(&path).unwrap());
+        assert_eq!(vec!["text.txt".to_string()], list(&dir));
+    }
+
+    #[test]
+    fn missing_file() {
+        let dir = TempDir::new("file-missing");
+        let replacer = Replacer::new(&create_map()).unwrap();
+        let result = replacer.replace_file(dir.join("none"), &FileOptions::new());
+        assert!(matches!(result, Err(ReplaceError::Io(_))));
+    }
+
+    fn list(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn create_map() -> HashMap<String, String> {
+        let mut map = HashMap::new();
+        map.insert("first".into(), "changed".into());
+        map.insert("another".into(), "something".into());
+        map
+    }
+}
@@ -0,0 +1,269 @@
+use crate::encoding::Encoding;
+use crate::replacer::{Edit, Replacer};
+use crate::scanner;
+use std::ops::Range;
+
+/// What to do with a replacement that takes more bytes than the text it replaces.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Leave the original text in place.
+    #[default]
+    Reject,
+    /// Cut the replacement down to the characters that fit.
+    Truncate,
+}
+
+/// Keeps every replacement at the byte length of the text it replaces, so offsets into the
+/// data stay valid. Shorter replacements are followed by `filler`, space by default.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FixedLength {
+    filler: char,
+    overflow: Overflow,
+}
+
+/// A replacement that did not fit, with `written` holding what went in its place. `None` means
+/// the original was kept.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    pub input: Range<usize>,
+    pub original: String,
+    pub replacement: String,
+    pub written: Option<String>,
+}
+
+impl Default for FixedLength {
+    fn default() -> Self {
+        Self {
+            filler: ' ',
+            overflow: Overflow::Reject,
+        }
+    }
+}
+
+impl FixedLength {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn filler(mut self, filler: char) -> Self {
+        self.filler = filler;
+        self
+    }
+
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    // Pads `value` to `len` bytes, if the filler divides what is left over.
+    fn pad(&self, encoding: Encoding, value: &str, len: usize) -> Option<String> {
+        let missing = len.checked_sub(encoding.encode(value).len())?;
+        let filler_len = encoding.encode(self.filler.encode_utf8(&mut [0; 4])).len();
+        if missing % filler_len != 0 {
+            return None;
+        }
+
+        let mut padded = value.to_string();
+        padded.extend(std::iter::repeat_n(self.filler, missing / filler_len));
+        Some(padded)
+    }
+}
+
+pub(crate) fn replace(
+    replacer: &Replacer,
+    encoding: Encoding,
+    bytes: &[u8],
+    options: &FixedLength,
+) -> (u128, Vec<u8>, Vec<Violation>) {
+    let (words, _) = scanner::words(encoding, replacer.tokenizer(), bytes, true);
+    let (edits, _) = replacer.edits(&words, |range| encoding.decode_str(&bytes[range]), true);
+    let mut fitted = Vec::with_capacity(edits.len());
+    let mut violations = vec![];
+    for edit in edits {
+        let len = edit.range.len();
+        if let Some(value) = options.pad(encoding, &edit.value, len) {
+            fitted.push(Edit { value, ..edit });
+            continue;
+        }
+
+        let written = match options.overflow {
+            Overflow::Reject => None,
+            Overflow::Truncate => {
+                let mut truncated = edit.value.clone();
+                while encoding.encode(&truncated).len() > len {
+                    truncated.pop();
+                }
+
+                options
+                    .pad(encoding, &truncated, len)
+                    .map(|value| (truncated, value))
+            }
+        };
+
+        violations.push(Violation {
+            input: edit.range.clone(),
+            original: edit.original.clone(),
+            replacement: edit.value.clone(),
+            written: written.as_ref().map(|(truncated, _)| truncated.clone()),
+        });
+
+        if let Some((_, value)) = written {
+            fitted.push(Edit { value, ..edit });
+        }
+    }
+
+    let new_bytes = scanner::splice(encoding, bytes, &fitted, |_, _| {});
+    (fitted.len() as u128, new_bytes, violations)
+}
+
+/// Like `replace` on ASCII, but keeps the length of `bytes` as a whole rather than of each
+/// replacement, padding or cutting the text at its end.
+pub(crate) fn replace_string(
+    replacer: &Replacer,
+    bytes: &[u8],
+    options: &FixedLength,
+) -> (u128, Vec<u8>, Vec<Violation>) {
+    let encoding = Encoding::Ascii;
+    let (words, _) = scanner::words(encoding, replacer.tokenizer(), bytes, true);
+    let (edits, _) = replacer.edits(&words, |range| encoding.decode_str(&bytes[range]), true);
+    let mut kept = Vec::with_capacity(edits.len());
+    let mut violations = vec![];
+    let mut new_len = bytes.len();
+    for edit in edits {
+        let grown_len = new_len - edit.range.len() + encoding.encode(&edit.value).len();
+        if grown_len > bytes.len() && options.overflow == Overflow::Reject {
+            violations.push(Violation {
+                input: edit.range.clone(),
+                original: edit.original.clone(),
+                replacement: edit.value.clone(),
+                written: None,
+            });
+            continue;
+        }
+
+        new_len = grown_len;
+        kept.push(edit);
+    }
+
+    let mut cut = vec![];
+    let new_bytes = scanner::splice(encoding, bytes, &kept, |edit, output| {
+        if output.end > bytes.len() {
+            let mut truncated = edit.value.clone();
+            while output.start + encoding.encode(&truncated).len() > bytes.len() {
+                truncated.pop();
+            }
+
+            cut.push(Violation {
+                input: edit.range.clone(),
+                original: edit.original.clone(),
+                replacement: edit.value.clone(),
+                written: Some(truncated),
+            });
+        }
+    });
+
+    let mut text = encoding.decode_str(&new_bytes).unwrap_or_default();
+    while text.len() > bytes.len() {
+        text.pop();
+    }
+
+    match options.pad(encoding, &text, bytes.len()) {
+        Some(text) => {
+            violations.extend(cut);
+            violations.sort_by_key(|violation| violation.input.start);
+            (kept.len() as u128, encoding.encode(&text), violations)
+        }
+        None => {
+            violations.extend(kept.iter().map(|edit| Violation {
+                input: edit.range.clone(),
+                original: edit.original.clone(),
+                replacement: edit.value.clone(),
+                written: None,
+            }));
+            violations.sort_by_key(|violation| violation.input.start);
+            (0, bytes.to_vec(), violations)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::encoding::Encoding;
+    use crate::fixed::{FixedLength, Overflow, Violation};
+    use crate::replacer::Replacer;
+    use crate::utils::get_le_16_bytes;
+
+    fn replacer() -> Replacer {
+        Replacer::new(&HashMap::from([
+            ("first".to_string(), "one".to_string()),
+            ("second".to_string(), "changed".to_string()),
+            ("third".to_string(), "drei".to_string()),
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn pads_shorter_replacements() {
+        let options = FixedLength::new();
+        let (count, result, violations) =
+            replacer().replace_fixed(Encoding::Ascii, b"First, third.", &options);
+        assert_eq!("One  , drei .", String::from_utf8(result).unwrap());
+        assert_eq!(count, 2);
+        assert!(violations.is_empty());
+
+        let options = FixedLength::new().filler('\0');
+        let input = get_le_16_bytes("first!");
+        let (_, result, _) = replacer().replace_fixed(Encoding::Utf16Le, &input, &options);
+        assert_eq!(get_le_16_bytes("one\0\0!"), result);
+    }
+
+    #[test]
+    fn rejects_longer_replacements() {
+        let options = FixedLength::new();
+        let (count, result, violations) =
+            replacer().replace_fixed(Encoding::Utf8, b"second first", &options);
+        assert_eq!("second one  ", String::from_utf8(result).unwrap());
+        assert_eq!(count, 1);
+        assert_eq!(
+            vec![Violation {
+                input: 0..6,
+                original: "second".to_string(),
+                replacement: "changed".to_string(),
+                written: None,
+            }],
+            violations
+        );
+    }
+
+    #[test]
+    fn truncates_longer_replacements() {
+        let options = FixedLength::new().overflow(Overflow::Truncate);
+        let input = get_le_16_bytes("Second first");
+        let (count, result, violations) =
+            replacer().replace_fixed(Encoding::Utf16Le, &input, &options);
+        assert_eq!(get_le_16_bytes("Change one  "), result);
+        assert_eq!(count, 2);
+        assert_eq!(Some("Change".to_string()), violations[0].written);
+    }
+
+    #[test]
+    fn multi_byte_replacements_are_measured_in_bytes() {
+        let replacer =
+            Replacer::new(&HashMap::from([("cafe".to_string(), "café".to_string())])).unwrap();
+        let options = FixedLength::new().overflow(Overflow::Truncate);
+        let (_, result, violations) = replacer.replace_fixed(Encoding::Utf8, b"cafe", &options);
+        assert_eq!("caf ", String::from_utf8(result).unwrap());
+        assert_eq!(Some("caf".to_string()), violations[0].written);
+
+        let (count, result, violations) = replacer.replace_fixed(
+            Encoding::Utf16Be,
+            &[0, b'c', 0, b'a', 0, b'f', 0, b'e'],
+            &options,
+        );
+        assert_eq!(count, 1);
+        assert!(violations.is_empty());
+        assert_eq!(vec![0, b'c', 0, b'a', 0, b'f', 0, 0xE9], result);
+    }
+}
@@ -1,4 +1,35 @@
 mod case_matcher;
 mod utils;
+mod scanner;
+mod stream;
+mod file;
+mod walk;
+mod diff;
+mod rule;
+mod callback;
+mod validate;
+mod unity;
+mod fixed;
+mod cstring;
+mod mixed;
 pub mod utf8_replacer;
 pub mod bytes_replacer;
+pub mod encoding;
+pub mod replacer;
+pub mod error;
+pub mod tokenizer;
+pub mod dictionary;
+
+pub use callback::WordContext;
+pub use case_matcher::CaseMode;
+pub use cstring::CStrings;
+pub use encoding::Encoding;
+pub use error::ReplaceError;
+pub use file::{FileCount, FileError, FileOptions};
+pub use fixed::{FixedLength, Overflow, Violation};
+pub use mixed::MixedText;
+pub use replacer::{Match, Replacer, ReplacerBuilder};
+pub use scanner::Alignment;
+pub use tokenizer::{CharClass, Tokenizer};
+pub use validate::Issue;
+pub use walk::WalkOptions;
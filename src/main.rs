@@ -0,0 +1,399 @@
+use std::env;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use text_replacer::{
+    Encoding, FileError, FileOptions, ReplaceError, Replacer, WalkOptions, dictionary,
+};
+
+const USAGE: &str = "\
+Usage: text-replacer [OPTIONS] <DICTIONARY> [FILES]...
+
+Replaces every dictionary word found in FILES, rewriting them in place. Directories are
+walked, skipping binary files, hidden files and whatever .gitignore files exclude. Without
+FILES, or for a FILE of -, the input is read from stdin and written to stdout. The dictionary
+can be a .json, .toml, .csv, .tsv or .po file.
+
+Options:
+  -e, --encoding <ENCODING>  utf8, ascii, utf16le, utf16be, utf32le, utf32be or auto [default: auto]
+      --include <GLOB>       Only replace files in walked directories whose path below the
+                             directory matches GLOB. Can be repeated, any match counts
+      --exclude <GLOB>       Skip files in walked directories whose path below the directory
+                             matches GLOB. Can be repeated, any match counts
+  -n, --dry-run              Print a unified diff of the changes instead of writing them, the
+                             counts go to stderr. UTF-16 and UTF-32 files are diffed as text
+  -c, --check                Report keys that can never match, duplicates and replacements
+                             that contain other keys, without replacing anything
+  -h, --help                 Print this help
+";
+
+const ENCODINGS: [(&str, Encoding); 6] = [
+    ("utf8", Encoding::Utf8),
+    ("ascii", Encoding::Ascii),
+    ("utf16le", Encoding::Utf16Le),
+    ("utf16be", Encoding::Utf16Be),
+    ("utf32le", Encoding::Utf32Le),
+    ("utf32be", Encoding::Utf32Be),
+];
+
+#[derive(Debug, PartialEq)]
+struct Args {
+    dictionary: PathBuf,
+    files: Vec<PathBuf>,
+    // None detects the encoding of every input on its own.
+    encoding: Option<Encoding>,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    dry_run: bool,
+    check: bool,
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Run(Args),
+    Help,
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(env::args().skip(1)) {
+        Ok(Command::Run(args)) => args,
+        Ok(Command::Help) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("text-replacer: {message}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("text-replacer: {message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    let mut positional = vec![];
+    let mut encoding = None;
+    let mut include = vec![];
+    let mut exclude = vec![];
+    let mut dry_run = false;
+    let mut check = false;
+    let mut options_done = false;
+    while let Some(arg) = args.next() {
+        // A lone "-" is stdin, not an option.
+        if options_done || arg == "-" || !arg.starts_with('-') {
+            positional.push(PathBuf::from(arg));
+            continue;
+        }
+
+        match arg.as_str() {
+            "--" => options_done = true,
+            "-h" | "--help" => return Ok(Command::Help),
+            "-n" | "--dry-run" => dry_run = true,
+            "-c" | "--check" => check = true,
+            "-e" | "--encoding" => {
+                let name = args.next().ok_or(format!("{arg} needs a value"))?;
+                encoding = parse_encoding(&name)?;
+            }
+            "--include" => include.push(args.next().ok_or(format!("{arg} needs a value"))?),
+            "--exclude" => exclude.push(args.next().ok_or(format!("{arg} needs a value"))?),
+            _ => match arg.split_once('=') {
+                Some(("--encoding", name)) => encoding = parse_encoding(name)?,
+                Some(("--include", glob)) => include.push(glob.to_string()),
+                Some(("--exclude", glob)) => exclude.push(glob.to_string()),
+                _ => return Err(format!("unknown option {arg}")),
+            },
+        }
+    }
+
+    if positional.is_empty() {
+        return Err("missing dictionary file".to_string());
+    }
+
+    let dictionary = positional.remove(0);
+    if dry_run && (positional.is_empty() || positional.iter().any(|path| is_stdin(path))) {
+        return Err("--dry-run needs files to compare against".to_string());
+    }
+
+    if check && !positional.is_empty() {
+        return Err("--check only takes the dictionary".to_string());
+    }
+
+    Ok(Command::Run(Args {
+        dictionary,
+        files: positional,
+        encoding,
+        include,
+        exclude,
+        dry_run,
+        check,
+    }))
+}
+
+fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+fn parse_encoding(name: &str) -> Result<Option<Encoding>, String> {
+    let name = name.to_ascii_lowercase().replace('-', "");
+    if name == "auto" {
+        return Ok(None);
+    }
+
+    ENCODINGS
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|&(_, encoding)| Some(encoding))
+        .ok_or(format!("unknown encoding {name}"))
+}
+
+fn encoding_name(encoding: Encoding) -> &'static str {
+    ENCODINGS
+        .iter()
+        .find(|&&(_, known)| known == encoding)
+        .map_or("unknown", |(name, _)| name)
+}
+
+fn run(args: &Args) -> Result<(), String> {
+    let dictionary = dictionary::load(&args.dictionary).map_err(|error| match error {
+        ReplaceError::Io(error) => format!("{}: {error}", args.dictionary.display()),
+        error => error.to_string(),
+    })?;
+
+    if args.check {
+        let issues = Replacer::builder().validate(&dictionary);
+        for issue in &issues {
+            println!("{}: {issue}", args.dictionary.display());
+        }
+
+        return match issues.len() {
+            0 => Ok(()),
+            count => Err(format!("{count} problems found")),
+        };
+    }
+
+    let replacer = Replacer::new(&dictionary).map_err(|error| error.to_string())?;
+    for key in replacer.skipped_keys() {
+        eprintln!(
+            "{}: skipping {key:?}, it can never match a word",
+            args.dictionary.display()
+        );
+    }
+
+    if args.files.is_empty() {
+        let count = replace_stdin(&replacer, args.encoding).map_err(|error| error.to_string())?;
+        eprintln!("{count} replacements");
+        return Ok(());
+    }
+
+    let mut options = FileOptions::new().dry_run(args.dry_run);
+    if let Some(encoding) = args.encoding {
+        options = options.encoding(encoding);
+    }
+
+    let mut walk_options = WalkOptions::new().file_options(options.clone());
+    for glob in &args.include {
+        walk_options = walk_options.include(glob);
+    }
+
+    for glob in &args.exclude {
+        walk_options = walk_options.exclude(glob);
+    }
+
+    // A dry run prints the diff to stdout, so it can be piped straight into `git apply`. Text
+    // read from stdin goes back out on stdout too.
+    let to_stderr = args.dry_run || args.files.iter().any(|path| is_stdin(path));
+    let report = |line: String| match to_stderr {
+        true => eprintln!("{line}"),
+        false => println!("{line}"),
+    };
+
+    let mut total = 0;
+    let mut reported = 0;
+    let mut failures = 0;
+    for path in &args.files {
+        if is_stdin(path) {
+            let count =
+                replace_stdin(&replacer, args.encoding).map_err(|error| format!("-: {error}"))?;
+            report(format!("-: {count} replacements"));
+            total += count;
+            reported += 1;
+            continue;
+        }
+
+        let (counts, failed) = if path.is_dir() {
+            replacer
+                .replace_dir(path, &walk_options)
+                .map_err(|error| format!("{}: {error}", path.display()))?
+        } else {
+            match replacer.replace_file(path, &options) {
+                Ok(file) => (vec![file], vec![]),
+                Err(error) => {
+                    let path = path.clone();
+                    (vec![], vec![FileError { path, error }])
+                }
+            }
+        };
+
+        // A file that fails is reported and the rest are still replaced.
+        for FileError { path, error } in &failed {
+            eprintln!("text-replacer: {}: {error}", path.display());
+        }
+
+        failures += failed.len();
+        for file in counts {
+            if let Some(diff) = &file.diff {
+                print!("{diff}");
+            }
+
+            report(format!(
+                "{}: {} replacements ({})",
+                file.path.display(),
+                file.count,
+                encoding_name(file.encoding)
+            ));
+            total += file.count;
+            reported += 1;
+        }
+    }
+
+    if reported > 1 {
+        report(format!("total: {total} replacements"));
+    }
+
+    match failures {
+        0 => Ok(()),
+        1 => Err("1 file could not be replaced".to_string()),
+        count => Err(format!("{count} files could not be replaced")),
+    }
+}
+
+fn replace_stdin(replacer: &Replacer, encoding: Option<Encoding>) -> Result<u128, ReplaceError> {
+    let stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+    match encoding {
+        Some(encoding) => replacer.replace_stream(encoding, stdin, stdout),
+        // Detection needs to see the input, so it is read whole.
+        None => {
+            let mut bytes = vec![];
+            io::BufReader::new(stdin).read_to_end(&mut bytes)?;
+            let (_, count, new_bytes) = replacer.replace_auto(&bytes);
+            stdout.write_all(&new_bytes)?;
+            stdout.flush()?;
+            Ok(count)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{Args, Command, parse_args};
+    use text_replacer::Encoding;
+
+    #[test]
+    fn parses_files_and_encoding() {
+        let command = parse(&["-e", "utf16le", "dict.json", "a.txt", "b.txt"]);
+        assert_eq!(
+            Ok(Command::Run(Args {
+                dictionary: PathBuf::from("dict.json"),
+                files: vec![PathBuf::from("a.txt"), PathBuf::from("b.txt")],
+                encoding: Some(Encoding::Utf16Le),
+                include: vec![],
+                exclude: vec![],
+                dry_run: false,
+                check: false,
+            })),
+            command
+        );
+    }
+
+    #[test]
+    fn defaults_to_auto_and_stdin() {
+        assert_eq!(
+            Ok(Command::Run(Args {
+                dictionary: PathBuf::from("dict.csv"),
+                files: vec![],
+                encoding: None,
+                include: vec![],
+                exclude: vec![],
+                dry_run: false,
+                check: false,
+            })),
+            parse(&["dict.csv"])
+        );
+    }
+
+    #[test]
+    fn encoding_names() {
+        for (name, expected) in [
+            ("--encoding=utf8", Some(Encoding::Utf8)),
+            ("--encoding=UTF-16BE", Some(Encoding::Utf16Be)),
+            ("--encoding=ascii", Some(Encoding::Ascii)),
+            ("--encoding=auto", None),
+        ] {
+            match parse(&[name, "dict.json"]) {
+                Ok(Command::Run(args)) => assert_eq!(expected, args.encoding),
+                other => panic!("{name}: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn include_and_exclude_globs() {
+        match parse(&[
+            "--include",
+            "*.txt",
+            "--exclude=build/**",
+            "dict.json",
+            "src",
+        ]) {
+            Ok(Command::Run(args)) => {
+                assert_eq!(vec!["*.txt".to_string()], args.include);
+                assert_eq!(vec!["build/**".to_string()], args.exclude);
+                assert_eq!(vec![PathBuf::from("src")], args.files);
+            }
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn files_after_double_dash() {
+        match parse(&["dict.json", "--", "-e"]) {
+            Ok(Command::Run(args)) => assert_eq!(vec![PathBuf::from("-e")], args.files),
+            other => panic!("{other:?}"),
+        }
+    }
+
+    #[test]
+    fn dash_is_stdin() {
+        match parse(&["dict.json", "-"]) {
+            Ok(Command::Run(args)) => assert_eq!(vec![PathBuf::from("-")], args.files),
+            other => panic!("{other:?}"),
+        }
+
+        assert!(parse(&["--dry-run", "dict.json", "-"]).is_err());
+    }
+
+    #[test]
+    fn usage_errors() {
+        assert_eq!(Ok(Command::Help), parse(&["dict.json", "--help"]));
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["-e"]).is_err());
+        assert!(parse(&["-e", "latin1", "dict.json"]).is_err());
+        assert!(parse(&["--verbose", "dict.json"]).is_err());
+        assert!(parse(&["--dry-run", "dict.json"]).is_err());
+        assert!(parse(&["--check", "dict.json", "a.txt"]).is_err());
+    }
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+}
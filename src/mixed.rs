@@ -0,0 +1,218 @@
+use crate::encoding::{Encoding, Unit};
+use crate::replacer::Replacer;
+use std::collections::HashMap;
+
+// On a full tie the later one wins, so UTF-16LE is preferred as the more common of the two.
+const CANDIDATES: [Encoding; 3] = [Encoding::Utf8, Encoding::Utf16Be, Encoding::Utf16Le];
+const WIDE: [Encoding; 2] = [Encoding::Utf16Be, Encoding::Utf16Le];
+
+/// Finds runs of text in binary data where 8-bit and UTF-16 strings sit side by side, and
+/// replaces each run in its own encoding. Runs shorter than `min_len` characters are skipped.
+/// UTF-16 is only recognized for scripts below U+2000, as CJK text in UTF-16 cannot be told
+/// apart from 8-bit text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MixedText {
+    min_len: usize,
+}
+
+impl Default for MixedText {
+    fn default() -> Self {
+        Self { min_len: 4 }
+    }
+}
+
+impl MixedText {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn min_len(mut self, min_len: usize) -> Self {
+        self.min_len = min_len;
+        self
+    }
+
+    // The most text-like run starting at the front of `bytes`. 8-bit runs without any multibyte
+    // characters are reported as ASCII.
+    fn run(&self, bytes: &[u8], encodings: &[Encoding]) -> Option<Run> {
+        encodings
+            .iter()
+            .copied()
+            .map(|encoding| Run::read(encoding, bytes))
+            .filter(|run| run.chars >= self.min_len.max(1) && run.plausible())
+            .max_by_key(|run| (run.ascii, run.len))
+    }
+}
+
+struct Run {
+    encoding: Encoding,
+    len: usize,
+    chars: usize,
+    ascii: usize,
+    // Characters below U+2000, the ones with a control byte in UTF-16.
+    below_2000: usize,
+}
+
+impl Run {
+    fn read(encoding: Encoding, bytes: &[u8]) -> Run {
+        let mut run = Run {
+            encoding,
+            len: 0,
+            chars: 0,
+            ascii: 0,
+            below_2000: 0,
+        };
+        while let Unit::Char(ch, len) = encoding.decode(&bytes[run.len..]) {
+            if ch.is_control() && !matches!(ch, '\t' | '\n' | '\r') {
+                break;
+            }
+
+            // Pairs of 8-bit text are left for the 8-bit run that starts here.
+            if run.chars == 0 && encoding != Encoding::Utf8 && ch >= '\u{2000}' {
+                break;
+            }
+
+            run.len += len;
+            run.chars += 1;
+            run.ascii += usize::from(ch.is_ascii());
+            run.below_2000 += usize::from(ch < '\u{2000}');
+        }
+
+        if encoding == Encoding::Utf8 && run.ascii == run.chars {
+            run.encoding = Encoding::Ascii;
+        }
+
+        run
+    }
+
+    // 8-bit text read as UTF-16 pairs up two printable bytes, which always lands at U+2000 or
+    // above. So UTF-16 runs have to be mostly below it, which leaves out CJK text in UTF-16.
+    fn plausible(&self) -> bool {
+        match self.encoding {
+            Encoding::Utf16Le | Encoding::Utf16Be => self.below_2000 * 2 >= self.chars,
+            _ => true,
+        }
+    }
+
+    // A UTF-16 run read one byte early pairs up the wrong bytes, which mostly shows as fewer
+    // ASCII characters than the run a byte later. Text like "\0a\0b\0" reads the same either
+    // way, and is taken as UTF-16LE.
+    fn misaligned(&self, next: &Run) -> bool {
+        let other = |run: &Run| run.chars - run.ascii;
+        let little_endian =
+            next.encoding == Encoding::Utf16Le && self.encoding == Encoding::Utf16Be;
+        next.ascii > self.ascii
+            || (next.ascii == self.ascii
+                && next.ascii > 0
+                && (other(next) < other(self) || (other(next) == other(self) && little_endian)))
+    }
+}
+
+pub(crate) fn replace(
+    replacer: &Replacer,
+    bytes: &[u8],
+    options: &MixedText,
+) -> (HashMap<Encoding, u128>, Vec<u8>) {
+    let mut counts = HashMap::from([
+        (Encoding::Ascii, 0),
+        (Encoding::Utf8, 0),
+        (Encoding::Utf16Le, 0),
+        (Encoding::Utf16Be, 0),
+    ]);
+    let mut new_bytes = Vec::with_capacity(bytes.len());
+    let mut cursor = 0;
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let Some(run) = options.run(&bytes[pos..], &CANDIDATES) else {
+            pos += 1;
+            continue;
+        };
+
+        let next = match run.encoding {
+            Encoding::Utf16Le | Encoding::Utf16Be => options.run(&bytes[pos + 1..], &WIDE),
+            _ => None,
+        };
+        if next.is_some_and(|next| run.misaligned(&next)) {
+            pos += 1;
+            continue;
+        }
+
+        let end = pos + run.len;
+        let (count, new_run) = replacer.replace(run.encoding, &bytes[pos..end]);
+        *counts.entry(run.encoding).or_default() += count;
+        new_bytes.extend_from_slice(&bytes[cursor..pos]);
+        new_bytes.extend(new_run);
+        cursor = end;
+        pos = end;
+    }
+
+    new_bytes.extend_from_slice(&bytes[cursor..]);
+    (counts, new_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::encoding::Encoding;
+    use crate::mixed::MixedText;
+    use crate::replacer::Replacer;
+    use crate::utils::{get_be_16_bytes, get_le_16_bytes};
+
+    fn blob(ascii: &str, wide: &str, utf8: &str, big: &str) -> Vec<u8> {
+        [
+            b"\x00\x01".as_slice(),
+            ascii.as_bytes(),
+            b"\0\0",
+            &get_le_16_bytes(wide),
+            b"\0\0\x8b\xc3",
+            utf8.as_bytes(),
+            b"\0\x02",
+            &get_be_16_bytes(big),
+            b"\xd8\x00\x90fi",
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn replaces_each_run_in_its_encoding() {
+        let replacer = Replacer::new(&HashMap::from([
+            ("first".to_string(), "changed".to_string()),
+            ("grüße".to_string(), "hallo".to_string()),
+        ]))
+        .unwrap();
+        let original = blob("first run", "first wide", "Grüße first", "the first");
+        let (counts, result) = replacer.replace_mixed(&original, &MixedText::new());
+        assert_eq!(
+            blob(
+                "changed run",
+                "changed wide",
+                "Hallo changed",
+                "the changed"
+            ),
+            result
+        );
+        assert_eq!(
+            HashMap::from([
+                (Encoding::Ascii, 1),
+                (Encoding::Utf8, 2),
+                (Encoding::Utf16Le, 1),
+                (Encoding::Utf16Be, 1),
+            ]),
+            counts
+        );
+    }
+
+    #[test]
+    fn short_runs_are_left_alone() {
+        let replacer =
+            Replacer::new(&HashMap::from([("ok".to_string(), "fine".to_string())])).unwrap();
+        let original = [b"\x01ok\x02".as_slice(), &get_le_16_bytes("ok"), b"\x03"].concat();
+        let (counts, result) = replacer.replace_mixed(&original, &MixedText::new());
+        assert_eq!(original, result);
+        assert!(counts.values().all(|&count| count == 0));
+
+        let (counts, _) = replacer.replace_mixed(&original, &MixedText::new().min_len(2));
+        assert_eq!(1, counts[&Encoding::Ascii]);
+        assert_eq!(1, counts[&Encoding::Utf16Le]);
+    }
+}
@@ -0,0 +1,886 @@
+use crate::callback::{self, WordContext};
+use crate::case_matcher::CaseMode;
+use crate::cstring::{self, CStrings};
+use crate::encoding::Encoding;
+use crate::error::ReplaceError;
+use crate::file::{self, FileCount, FileError, FileOptions};
+use crate::fixed::{self, FixedLength, Violation};
+use crate::mixed::{self, MixedText};
+use crate::rule::Rule;
+use crate::scanner::{self, Alignment, Word};
+use crate::stream;
+use crate::tokenizer::Tokenizer;
+use crate::unity;
+use crate::validate::{self, Issue};
+use crate::walk::{self, WalkOptions};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::ops::Range;
+use std::path::Path;
+
+pub struct Replacer {
+    dictionary: HashMap<String, Entry>,
+    max_words: usize,
+    case_mode: CaseMode,
+    case_sensitive: bool,
+    tokenizer: Tokenizer,
+    rules: Vec<Rule>,
+    skipped: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ReplacerBuilder {
+    case_mode: CaseMode,
+    case_sensitive: bool,
+    strict: bool,
+    tokenizer: Tokenizer,
+    rules: Vec<(String, String)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Match {
+    pub key: String,
+    pub original: String,
+    pub replacement: String,
+    pub input: Range<usize>,
+    pub output: Range<usize>,
+}
+
+struct Entry {
+    key: String,
+    value: String,
+}
+
+pub(crate) struct Edit<'a> {
+    pub(crate) range: Range<usize>,
+    pub(crate) key: &'a str,
+    pub(crate) original: String,
+    pub(crate) value: String,
+}
+
+impl Edit<'_> {
+    pub(crate) fn to_match(&self, offset: usize, output: Range<usize>) -> Match {
+        Match {
+            key: self.key.to_string(),
+            original: self.original.clone(),
+            replacement: self.value.clone(),
+            input: self.range.start + offset..self.range.end + offset,
+            output,
+        }
+    }
+}
+
+impl ReplacerBuilder {
+    pub fn case_mode(mut self, case_mode: CaseMode) -> Self {
+        self.case_mode = case_mode;
+        self
+    }
+
+    /// Keys only match words written exactly the same way, and values are used as written. Every
+    /// `Replacer` method honours it.
+    pub fn case_sensitive(mut self, case_sensitive: bool) -> Self {
+        self.case_sensitive = case_sensitive;
+        self
+    }
+
+    /// Fails the build on a key that can never match a word, instead of leaving it out.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn tokenizer(mut self, tokenizer: Tokenizer) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    /// Replaces every single word that `pattern` matches as a whole, after the dictionary did not
+    /// match at that word. Rules are tried in the order they were added.
+    pub fn rule(mut self, pattern: &str, template: &str) -> Self {
+        self.rules.push((pattern.to_string(), template.to_string()));
+        self
+    }
+
+    /// Reports the problems in `dictionary` that `build` would silently work around, including
+    /// the keys it leaves out.
+    pub fn validate(&self, dictionary: &HashMap<String, String>) -> Vec<Issue> {
+        let builder = Self {
+            strict: false,
+            rules: vec![],
+            ..self.clone()
+        };
+        let replacer = builder
+            .build(dictionary)
+            .expect("only rules and strict builds fail");
+        validate::validate(dictionary, &replacer, self.case_sensitive)
+    }
+
+    pub fn build(self, dictionary: &HashMap<String, String>) -> Result<Replacer, ReplaceError> {
+        let mut entries: Vec<(&String, &String)> = dictionary.iter().collect();
+        entries.sort();
+
+        // Keys are folded once here so lookups only fold the word. When two keys fold to the
+        // same value, the one that was already folded wins, otherwise the first in order.
+        let mut folded: HashMap<String, Entry> = HashMap::with_capacity(entries.len());
+        let mut skipped = vec![];
+        let mut max_words = 1;
+        for (key, value) in entries {
+            let normalized = normalize(key, self.case_sensitive);
+            let tokenizer = &self.tokenizer;
+            if !tokenizer.is_valid_key(&normalized) {
+                if self.strict {
+                    return Err(ReplaceError::InvalidKey(key.clone()));
+                }
+
+                skipped.push(key.clone());
+                continue;
+            }
+
+            if *key == normalized || !folded.contains_key(&normalized) {
+                max_words = max_words.max(tokenizer.count_words(&normalized));
+                folded.insert(
+                    normalized,
+                    Entry {
+                        key: key.clone(),
+                        value: value.clone(),
+                    },
+                );
+            }
+        }
+
+        let rules = self
+            .rules
+            .iter()
+            .map(|(pattern, template)| Rule::new(pattern, template, self.case_sensitive))
+            .collect::<Result<Vec<Rule>, ReplaceError>>()?;
+
+        Ok(Replacer {
+            dictionary: folded,
+            max_words,
+            case_mode: match self.case_sensitive {
+                true => CaseMode::AsWritten,
+                false => self.case_mode,
+            },
+            case_sensitive: self.case_sensitive,
+            tokenizer: self.tokenizer,
+            rules,
+            skipped,
+        })
+    }
+}
+
+impl Replacer {
+    pub fn new(dictionary: &HashMap<String, String>) -> Result<Self, ReplaceError> {
+        Self::builder().build(dictionary)
+    }
+
+    pub fn builder() -> ReplacerBuilder {
+        ReplacerBuilder::default()
+    }
+
+    pub(crate) fn tokenizer(&self) -> &Tokenizer {
+        &self.tokenizer
+    }
+
+    /// The keys left out of the dictionary because they can never match a word, in order.
+    pub fn skipped_keys(&self) -> &[String] {
+        &self.skipped
+    }
+
+    pub fn replace(&self, encoding: Encoding, bytes: &[u8]) -> (u128, Vec<u8>) {
+        let (words, _) = scanner::words(encoding, &self.tokenizer, bytes, true);
+        let (edits, _) = self.edits(&words, |range| encoding.decode_str(&bytes[range]), true);
+        (
+            edits.len() as u128,
+            scanner::splice(encoding, bytes, &edits, |_, _| {}),
+        )
+    }
+
+    /// Same as `replace`, with code units of wide encodings only read where `alignment` allows.
+    /// Binary data then cannot produce words out of bytes from two neighbouring units.
+    pub fn replace_aligned(
+        &self,
+        encoding: Encoding,
+        bytes: &[u8],
+        alignment: Alignment,
+    ) -> (u128, Vec<u8>) {
+        let words = scanner::aligned_words(encoding, &self.tokenizer, bytes, alignment);
+        let (edits, _) = self.edits(&words, |range| encoding.decode_str(&bytes[range]), true);
+        (
+            edits.len() as u128,
+            scanner::splice(encoding, bytes, &edits, |_, _| {}),
+        )
+    }
+
+    /// Asks `replacement` for every word instead of looking it up in the dictionary, with words
+    /// found by the tokenizer of this replacer and cased by its case options. Words it returns
+    /// `None` for are kept.
+    pub fn replace_with<'r>(
+        &self,
+        bytes: &[u8],
+        encoding: Encoding,
+        replacement: impl FnMut(&str, &WordContext) -> Option<Cow<'r, str>>,
+    ) -> (u128, Vec<u8>) {
+        callback::replace(
+            encoding,
+            &self.tokenizer,
+            self.case_mode,
+            bytes,
+            replacement,
+        )
+    }
+
+    pub fn replace_with_report(&self, encoding: Encoding, bytes: &[u8]) -> (Vec<Match>, Vec<u8>) {
+        let (words, _) = scanner::words(encoding, &self.tokenizer, bytes, true);
+        let (edits, _) = self.edits(&words, |range| encoding.decode_str(&bytes[range]), true);
+        let mut matches = Vec::with_capacity(edits.len());
+        let new_bytes = scanner::splice(encoding, bytes, &edits, |edit, output| {
+            matches.push(edit.to_match(0, output))
+        });
+
+        (matches, new_bytes)
+    }
+
+    pub fn replace_text(&self, content: &str) -> (u128, String) {
+        let (matches, new_content) = self.replace_text_with_report(content);
+        (matches.len() as u128, new_content)
+    }
+
+    pub fn replace_text_with_report(&self, content: &str) -> (Vec<Match>, String) {
+        let (words, _) = scanner::words(Encoding::Utf8, &self.tokenizer, content.as_bytes(), true);
+        let (edits, _) = self.edits(&words, |range| Some(content[range].to_string()), true);
+        let mut matches = Vec::with_capacity(edits.len());
+        let mut new_content = String::with_capacity(content.len());
+        let mut cursor = 0;
+        for edit in &edits {
+            new_content += &content[cursor..edit.range.start];
+            let start = new_content.len();
+            new_content += &edit.value;
+            matches.push(edit.to_match(0, start..new_content.len()));
+            cursor = edit.range.end;
+        }
+
+        new_content += &content[cursor..];
+        (matches, new_content)
+    }
+
+    pub fn replace_ascii(&self, bytes: &[u8]) -> (u128, Vec<u8>) {
+        self.replace(Encoding::Ascii, bytes)
+    }
+
+    pub fn replace_le_16(&self, bytes: &[u8]) -> (u128, Vec<u8>) {
+        self.replace(Encoding::Utf16Le, bytes)
+    }
+
+    pub fn replace_be_16(&self, bytes: &[u8]) -> (u128, Vec<u8>) {
+        self.replace(Encoding::Utf16Be, bytes)
+    }
+
+    pub fn replace_le_32(&self, bytes: &[u8]) -> (u128, Vec<u8>) {
+        self.replace(Encoding::Utf32Le, bytes)
+    }
+
+    pub fn replace_be_32(&self, bytes: &[u8]) -> (u128, Vec<u8>) {
+        self.replace(Encoding::Utf32Be, bytes)
+    }
+
+    pub fn replace_auto(&self, bytes: &[u8]) -> (Encoding, u128, Vec<u8>) {
+        let encoding = Encoding::detect(bytes);
+        let bom = if bytes.starts_with(encoding.bom()) {
+            encoding.bom().len()
+        } else {
+            0
+        };
+
+        let (count, new_bytes) = self.replace(encoding, &bytes[bom..]);
+        (encoding, count, [&bytes[..bom], &new_bytes].concat())
+    }
+
+    /// Replaces without changing the length of the input, for binaries and formats that refer to
+    /// data by offset. Replacements that do not fit come back as violations.
+    pub fn replace_fixed(
+        &self,
+        encoding: Encoding,
+        bytes: &[u8],
+        options: &FixedLength,
+    ) -> (u128, Vec<u8>, Vec<Violation>) {
+        fixed::replace(self, encoding, bytes, options)
+    }
+
+    /// Replaces only inside the NUL-terminated strings of a binary, leaving code and other data
+    /// alone even where it looks like text.
+    pub fn replace_c_strings(
+        &self,
+        bytes: &[u8],
+        options: &CStrings,
+    ) -> (u128, Vec<u8>, Vec<Violation>) {
+        cstring::replace(self, bytes, options)
+    }
+
+    /// Replaces in data that holds ASCII, UTF-8 and UTF-16 strings side by side, each run in its
+    /// own encoding. The counts are broken down by encoding.
+    pub fn replace_mixed(
+        &self,
+        bytes: &[u8],
+        options: &MixedText,
+    ) -> (HashMap<Encoding, u128>, Vec<u8>) {
+        mixed::replace(self, bytes, options)
+    }
+
+    /// Replaces words in the strings of a Unity SerializedFile (a .assets file or one taken out
+    /// of an asset bundle), keeping the file loadable when the strings change length. The file
+    /// has to include type trees.
+    pub fn replace_unity(&self, bytes: &[u8]) -> Result<(u128, Vec<u8>), ReplaceError> {
+        unity::replace(self, bytes)
+    }
+
+    pub fn replace_file(
+        &self,
+        path: impl AsRef<Path>,
+        options: &FileOptions,
+    ) -> Result<FileCount, ReplaceError> {
+        file::replace(self, path.as_ref(), options)
+    }
+
+    /// Replaces every file under `root` that `options` picks. Files that fail are returned next
+    /// to the counts, with their error, and do not stop the others.
+    pub fn replace_dir(
+        &self,
+        root: impl AsRef<Path>,
+        options: &WalkOptions,
+    ) -> Result<(Vec<FileCount>, Vec<FileError>), ReplaceError> {
+        walk::replace(self, root.as_ref(), options)
+    }
+
+    pub fn replace_stream(
+        &self,
+        encoding: Encoding,
+        reader: impl Read,
+        writer: impl Write,
+    ) -> Result<u128, ReplaceError> {
+        stream::replace(self, encoding, reader, writer, stream::CHUNK_SIZE, None)
+    }
+
+    pub fn replace_stream_with_report(
+        &self,
+        encoding: Encoding,
+        reader: impl Read,
+        writer: impl Write,
+    ) -> Result<Vec<Match>, ReplaceError> {
+        let mut matches = vec![];
+        stream::replace(
+            self,
+            encoding,
+            reader,
+            writer,
+            stream::CHUNK_SIZE,
+            Some(&mut matches),
+        )?;
+        Ok(matches)
+    }
+
+    /// Walks the words in order and picks the longest phrase starting at each one. `gap` decodes
+    /// the input between two neighbouring words, returning `None` when it is not text. Unless
+    /// `complete` is set, it stops at the first word whose phrases could reach past `words`, and
+    /// returns the index of that word.
+    pub(crate) fn edits(
+        &self,
+        words: &[Word],
+        gap: impl Fn(Range<usize>) -> Option<String>,
+        complete: bool,
+    ) -> (Vec<Edit<'_>>, usize) {
+        let mut edits = vec![];
+        let mut index = 0;
+        while index < words.len() {
+            if !complete && index + self.max_words > words.len() {
+                break;
+            }
+
+            match self.longest_match(&words[index..], &gap) {
+                Some((len, entry, original)) => {
+                    edits.push(Edit {
+                        range: words[index].range.start..words[index + len - 1].range.end,
+                        key: &entry.key,
+                        value: self.case_mode.apply(&original, &entry.value),
+                        original,
+                    });
+                    index += len;
+                }
+                None => {
+                    let word = &words[index];
+                    let rule = self
+                        .rules
+                        .iter()
+                        .find_map(|rule| rule.apply(&word.text).map(|value| (rule, value)));
+                    if let Some((rule, value)) = rule {
+                        edits.push(Edit {
+                            range: word.range.clone(),
+                            key: &rule.pattern,
+                            value: self.case_mode.apply(&word.text, &value),
+                            original: word.text.clone(),
+                        });
+                    }
+
+                    index += 1;
+                }
+            }
+        }
+
+        (edits, index)
+    }
+
+    fn longest_match(
+        &self,
+        words: &[Word],
+        gap: impl Fn(Range<usize>) -> Option<String>,
+    ) -> Option<(usize, &Entry, String)> {
+        let mut phrase = String::new();
+        let mut candidates = Vec::with_capacity(self.max_words);
+        for (index, word) in words.iter().take(self.max_words).enumerate() {
+            if index > 0 {
+                match gap(words[index - 1].range.end..word.range.start) {
+                    Some(text) => phrase += &text,
+                    None => break,
+                }
+            }
+
+            phrase += &word.text;
+            candidates.push(phrase.clone());
+        }
+
+        candidates
+            .into_iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, original)| {
+                self.dictionary
+                    .get(&normalize(&original, self.case_sensitive))
+                    .map(|entry| (index + 1, entry, original))
+            })
+    }
+}
+
+impl TryFrom<HashMap<String, String>> for Replacer {
+    type Error = ReplaceError;
+
+    fn try_from(dictionary: HashMap<String, String>) -> Result<Self, Self::Error> {
+        Self::new(&dictionary)
+    }
+}
+
+pub(crate) fn normalize(text: &str, case_sensitive: bool) -> String {
+    let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+    match case_sensitive {
+        true => text,
+        false => text.to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::case_matcher::CaseMode;
+    use crate::encoding::Encoding;
+    use crate::error::ReplaceError;
+    use crate::replacer::{Match, Replacer};
+    use crate::scanner::Alignment;
+    use crate::tokenizer::Tokenizer;
+    use crate::utils::{get_be_16_bytes, get_le_16_bytes};
+
+    #[test]
+    fn reuses_dictionary_across_encodings() {
+        let replacer = Replacer::new(&create_map()).unwrap();
+
+        let (count, result) = replacer.replace_text("First and another.");
+        assert_eq!("Changed and something.", result);
+        assert_eq!(count, 2);
+
+        let (count, result) = replacer.replace_ascii(b"First and another.");
+        assert_eq!(b"Changed and something.".to_vec(), result);
+        assert_eq!(count, 2);
+
+        let (count, result) = replacer.replace_le_16(&get_le_16_bytes("First and another."));
+        assert_eq!(get_le_16_bytes("Changed and something."), result);
+        assert_eq!(count, 2);
+
+        let (count, result) = replacer.replace_be_16(&get_be_16_bytes("First and another."));
+        assert_eq!(get_be_16_bytes("Changed and something."), result);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn folds_keys_once() {
+        let mut map = HashMap::new();
+        map.insert("First".to_string(), "changed".to_string());
+        let replacer = Replacer::try_from(map).unwrap();
+
+        let (count, result) = replacer.replace_text("first FIRST");
+        assert_eq!("changed CHANGED", result);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn lowercase_key_wins_when_keys_fold_together() {
+        let mut map = HashMap::new();
+        map.insert("US".to_string(), "wrong".to_string());
+        map.insert("us".to_string(), "we".to_string());
+        map.insert("Us".to_string(), "wrong".to_string());
+        let replacer = Replacer::new(&map).unwrap();
+
+        let (_, result) = replacer.replace_text("us");
+        assert_eq!("we", result);
+    }
+
+    #[test]
+    fn replaces_phrases() {
+        let replacer = Replacer::new(&create_phrase_map()).unwrap();
+
+        let (count, result) = replacer.replace_text("Please sign in to New York.");
+        assert_eq!("Please log in to Nyc.", result);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn longest_phrase_wins() {
+        let replacer = Replacer::new(&create_phrase_map()).unwrap();
+
+        let (count, result) = replacer.replace_text("new york city and new york and new");
+        assert_eq!("the big apple and nyc and old", result);
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn phrases_match_any_whitespace_between_words() {
+        let replacer = Replacer::new(&create_phrase_map()).unwrap();
+
+        let (count, result) = replacer.replace_text("SIGN\n  IN");
+        assert_eq!("LOG IN", result);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn phrases_need_the_same_punctuation() {
+        let replacer = Replacer::new(&create_phrase_map()).unwrap();
+
+        let (count, result) = replacer.replace_text("sign, in e-mail e mail");
+        assert_eq!("sign, in email e mail", result);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn replaces_phrases_in_every_encoding() {
+        let replacer = Replacer::new(&create_phrase_map()).unwrap();
+        let content = "Sign in, new york city";
+        let expected = "Log in, the big apple";
+
+        let (count, result) = replacer.replace_ascii(content.as_bytes());
+        assert_eq!(expected.as_bytes().to_vec(), result);
+        assert_eq!(count, 2);
+
+        let (count, result) = replacer.replace_le_16(&get_le_16_bytes(content));
+        assert_eq!(get_le_16_bytes(expected), result);
+        assert_eq!(count, 2);
+
+        let (count, result) = replacer.replace_be_16(&get_be_16_bytes(content));
+        assert_eq!(get_be_16_bytes(expected), result);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn phrases_do_not_match_across_binary_bytes() {
+        let replacer = Replacer::new(&create_phrase_map()).unwrap();
+        let content = [b"sign".to_vec(), vec![0xFF], b"in".to_vec()].concat();
+
+        let (count, result) = replacer.replace_ascii(&content);
+        assert_eq!(content, result);
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn aligned_units_do_not_straddle() {
+        let replacer = Replacer::new(&create_map()).unwrap();
+        // Read from an odd offset the second half is "first" in UTF-16LE.
+        let content = [
+            get_le_16_bytes("keep this"),
+            b"\x12\x01\0f\0i\0r\0s\0t\0\0".to_vec(),
+        ]
+        .concat();
+
+        let (count, _) = replacer.replace_le_16(&content);
+        assert_eq!(count, 1);
+
+        for alignment in [Alignment::From(0), Alignment::Auto] {
+            let (count, result) = replacer.replace_aligned(Encoding::Utf16Le, &content, alignment);
+            assert_eq!(content, result);
+            assert_eq!(count, 0);
+        }
+    }
+
+    #[test]
+    fn aligned_from_an_offset() {
+        let replacer = Replacer::new(&create_map()).unwrap();
+        let content = [b"\x07".as_slice(), &get_le_16_bytes("first, another")].concat();
+        let expected = [b"\x07".as_slice(), &get_le_16_bytes("changed, something")].concat();
+        for alignment in [Alignment::From(1), Alignment::Auto] {
+            let (count, result) = replacer.replace_aligned(Encoding::Utf16Le, &content, alignment);
+            assert_eq!(expected, result);
+            assert_eq!(count, 2);
+        }
+
+        let (count, _) = replacer.replace_aligned(Encoding::Utf16Le, &content, Alignment::From(0));
+        assert_eq!(count, 0);
+
+        let (count, result) =
+            replacer.replace_aligned(Encoding::Utf16Le, &content, Alignment::From(15));
+        assert_eq!(
+            [b"\x07".as_slice(), &get_le_16_bytes("first, something")].concat(),
+            result
+        );
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn reports_every_replacement() {
+        let replacer = Replacer::new(&create_phrase_map()).unwrap();
+
+        let (matches, result) = replacer.replace_text_with_report("Русский Sign in, new");
+        assert_eq!("Русский Log in, old", result);
+        assert_eq!(
+            vec![
+                Match {
+                    key: "sign in".into(),
+                    original: "Sign in".into(),
+                    replacement: "Log in".into(),
+                    input: 15..22,
+                    output: 15..21,
+                },
+                Match {
+                    key: "new".into(),
+                    original: "new".into(),
+                    replacement: "old".into(),
+                    input: 24..27,
+                    output: 23..26,
+                },
+            ],
+            matches
+        );
+    }
+
+    #[test]
+    fn reports_key_as_written_in_dictionary() {
+        let replacer = Replacer::new(&create_phrase_map()).unwrap();
+
+        let (matches, _) = replacer.replace_text_with_report("new york city");
+        assert_eq!("New York City", matches[0].key);
+    }
+
+    #[test]
+    fn reports_byte_ranges_in_utf16() {
+        let replacer = Replacer::new(&create_phrase_map()).unwrap();
+        let content = [vec![255], get_le_16_bytes(" new e-mail")].concat();
+
+        let (matches, result) = replacer.replace_with_report(Encoding::Utf16Le, &content);
+        assert_eq!([vec![255], get_le_16_bytes(" old email")].concat(), result);
+        assert_eq!(3..9, matches[0].input);
+        assert_eq!(3..9, matches[0].output);
+        assert_eq!(11..23, matches[1].input);
+        assert_eq!(11..21, matches[1].output);
+        for found in &matches {
+            assert_eq!(
+                get_le_16_bytes(&found.original),
+                content[found.input.clone()]
+            );
+            assert_eq!(
+                get_le_16_bytes(&found.replacement),
+                result[found.output.clone()]
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_keys_without_words() {
+        for key in ["", "   ", "..."] {
+            let mut map = create_map();
+            map.insert(key.into(), "value".into());
+            assert!(matches!(
+                Replacer::builder().strict(true).build(&map),
+                Err(ReplaceError::InvalidKey(invalid)) if invalid == key
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_keys_that_start_or_end_outside_a_word() {
+        for key in ["c++", "-dash", "U.S."] {
+            let mut map = create_map();
+            map.insert(key.into(), "value".into());
+            assert!(matches!(
+                Replacer::builder().strict(true).build(&map),
+                Err(ReplaceError::InvalidKey(invalid)) if invalid == key
+            ));
+        }
+    }
+
+    #[test]
+    fn callback_uses_tokenizer_and_case_options() {
+        let replacer = Replacer::builder()
+            .tokenizer(Tokenizer::new().join_chars(&['\'']))
+            .case_sensitive(true)
+            .build(&HashMap::new())
+            .unwrap();
+        let (count, result) = replacer.replace_with(b"Don't stop", Encoding::Utf8, |word, ctx| {
+            (word == "Don't").then(|| ctx.match_case("do not").into())
+        });
+        assert_eq!("do not stop", String::from_utf8(result).unwrap());
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn skips_keys_that_cannot_match() {
+        let mut map = create_map();
+        map.insert("U.S.".into(), "USA".into());
+        map.insert("c++".into(), "cpp".into());
+        let replacer = Replacer::new(&map).unwrap();
+        assert_eq!(["U.S.", "c++"], replacer.skipped_keys());
+
+        let (count, result) = replacer.replace_text("first in the U.S. and c++");
+        assert_eq!("changed in the U.S. and c++", result);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn empty_values_remove_the_word() {
+        let mut map = create_map();
+        map.insert("remove".into(), "".into());
+        let replacer = Replacer::new(&map).unwrap();
+
+        let (count, result) = replacer.replace_text("Remove first");
+        assert_eq!(" changed", result);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn keeps_camel_case() {
+        let mut map = create_map();
+        map.insert("getuser".into(), "fetch account".into());
+        map.insert("ipod".into(), "ipad".into());
+        let replacer = Replacer::new(&map).unwrap();
+
+        let (count, result) = replacer.replace_text("getUser GetUser GETUSER on my iPod");
+        assert_eq!("fetchAccount FetchAccount FETCH ACCOUNT on my iPad", result);
+        assert_eq!(count, 4);
+    }
+
+    #[test]
+    fn as_written_ignores_case_of_original() {
+        let mut map = create_map();
+        map.insert("phone".into(), "iPhone".into());
+        let replacer = Replacer::builder()
+            .case_mode(CaseMode::AsWritten)
+            .build(&map)
+            .unwrap();
+
+        let (count, result) = replacer.replace_text("Phone PHONE, First");
+        assert_eq!("iPhone iPhone, changed", result);
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn case_sensitive_keys() {
+        let mut map = create_map();
+        map.insert("US".into(), "United States".into());
+        map.insert("us".into(), "we".into());
+        map.insert("First".into(), "Initial".into());
+        let replacer = Replacer::builder()
+            .case_sensitive(true)
+            .build(&map)
+            .unwrap();
+
+        let (count, result) = replacer.replace_text("Tell us about the US, First and FIRST");
+        assert_eq!("Tell we about the United States, Initial and FIRST", result);
+        assert_eq!(count, 3);
+
+        let (count, result) = replacer.replace_text("first another Another");
+        assert_eq!("changed something Another", result);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn regex_rules_in_the_same_pass() {
+        let replacer = Replacer::builder()
+            .rule("colou?r", "color")
+            .rule(r"v(\d+)", "version $1")
+            .rule("first", "never used")
+            .build(&create_map())
+            .unwrap();
+
+        let content = "First Colour, v2 and v2b colours";
+        let (matches, result) = replacer.replace_with_report(Encoding::Utf8, content.as_bytes());
+        assert_eq!(
+            "Changed Color, version 2 and v2b colours",
+            String::from_utf8(result).unwrap()
+        );
+        let keys: Vec<&str> = matches.iter().map(|m| m.key.as_str()).collect();
+        assert_eq!(vec!["first", "colou?r", r"v(\d+)"], keys);
+        assert_eq!(replacer.replace_text(content).0, 3);
+    }
+
+    #[test]
+    fn invalid_rule() {
+        let result = Replacer::builder().rule("(", "x").build(&create_map());
+        assert!(matches!(result, Err(ReplaceError::InvalidPattern(_))));
+    }
+
+    #[test]
+    fn joiners_keep_words_together() {
+        let mut map = create_map();
+        map.insert("don't".into(), "do not".into());
+        map.insert("don".into(), "wrong".into());
+        let replacer = Replacer::builder()
+            .tokenizer(Tokenizer::new().join_chars(&['\'', '-']))
+            .build(&map)
+            .unwrap();
+
+        let (count, result) = replacer.replace_text("Don't 'first'- first-another don");
+        assert_eq!("Do not 'changed'- first-another wrong", result);
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn underscore_can_split_words() {
+        let mut map = create_map();
+        map.insert("localized".into(), "translated".into());
+        let text = "m_Localized first_another";
+
+        let (count, result) = Replacer::new(&map).unwrap().replace_text(text);
+        assert_eq!(text, result);
+        assert_eq!(count, 0);
+
+        let replacer = Replacer::builder()
+            .tokenizer(Tokenizer::new().extra_chars(&[]))
+            .build(&map)
+            .unwrap();
+        let (count, result) = replacer.replace_text(text);
+        assert_eq!("m_Translated changed_something", result);
+        assert_eq!(count, 3);
+    }
+
+    fn create_map() -> HashMap<String, String> {
+        let mut map = HashMap::new();
+        map.insert("first".into(), "changed".into());
+        map.insert("another".into(), "something".into());
+        map
+    }
+
+    fn create_phrase_map() -> HashMap<String, String> {
+        let mut map = HashMap::new();
+        map.insert("sign in".into(), "log in".into());
+        map.insert("new york".into(), "nyc".into());
+        map.insert("New York City".into(), "the big apple".into());
+        map.insert("new".into(), "old".into());
+        map.insert("e-mail".into(), "email".into());
+        map
+    }
+}
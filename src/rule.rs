@@ -0,0 +1,69 @@
+use crate::error::ReplaceError;
+use regex::{Regex, RegexBuilder};
+
+/// A regular expression matched against a whole word, with a replacement template that can
+/// refer to capture groups as `$1`, `${1}` or `${name}`.
+pub(crate) struct Rule {
+    pub(crate) pattern: String,
+    regex: Regex,
+    template: String,
+}
+
+impl Rule {
+    pub(crate) fn new(
+        pattern: &str,
+        template: &str,
+        case_sensitive: bool,
+    ) -> Result<Self, ReplaceError> {
+        let regex = RegexBuilder::new(&format!("^(?:{pattern})$"))
+            .case_insensitive(!case_sensitive)
+            .build()
+            .map_err(|error| ReplaceError::InvalidPattern(error.to_string()))?;
+
+        Ok(Self {
+            pattern: pattern.to_string(),
+            regex,
+            template: template.to_string(),
+        })
+    }
+
+    pub(crate) fn apply(&self, word: &str) -> Option<String> {
+        let captures = self.regex.captures(word)?;
+        let mut value = String::new();
+        captures.expand(&self.template, &mut value);
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::ReplaceError;
+    use crate::rule::Rule;
+
+    #[test]
+    fn matches_whole_words_only() {
+        let rule = Rule::new("colou?r", "color", false).unwrap();
+        assert_eq!(Some("color".to_string()), rule.apply("colour"));
+        assert_eq!(Some("color".to_string()), rule.apply("COLOR"));
+        assert_eq!(None, rule.apply("colours"));
+        assert_eq!(None, rule.apply("watercolor"));
+    }
+
+    #[test]
+    fn expands_captures() {
+        let rule = Rule::new(r"v(\d+)", "version $1", false).unwrap();
+        assert_eq!(Some("version 12".to_string()), rule.apply("v12"));
+
+        let rule = Rule::new(r"(?<name>\w+)_old", "${name}_new", true).unwrap();
+        assert_eq!(Some("value_new".to_string()), rule.apply("value_old"));
+        assert_eq!(None, rule.apply("value_OLD"));
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        assert!(matches!(
+            Rule::new("colou(r", "color", false),
+            Err(ReplaceError::InvalidPattern(_))
+        ));
+    }
+}
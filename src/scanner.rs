@@ -0,0 +1,215 @@
+use crate::encoding::{Encoding, Unit};
+use crate::replacer::Edit;
+use crate::tokenizer::Tokenizer;
+use std::ops::Range;
+
+pub(crate) struct Word {
+    pub(crate) text: String,
+    pub(crate) range: Range<usize>,
+}
+
+/// Where the code units of a wide encoding may start.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Alignment {
+    /// At any byte, so text is found even when it sits on an odd offset.
+    #[default]
+    Any,
+    /// Only a whole number of code units past the given offset, so no unit is read across two
+    /// real ones. The bytes before the offset are left alone.
+    From(usize),
+    /// Whichever alignment from the start of the input reads as more text.
+    Auto,
+}
+
+enum Run {
+    Word(Word),
+    Misaligned,
+    NeedMore,
+}
+
+/// Collects the words in `bytes` along with the offset scanning has to resume from. Unless
+/// `eof` is set, a word or code unit that runs into the end of `bytes` is left for the next call.
+pub(crate) fn words(
+    encoding: Encoding,
+    tokenizer: &Tokenizer,
+    bytes: &[u8],
+    eof: bool,
+) -> (Vec<Word>, usize) {
+    let mut words = vec![];
+    let mut cursor = 0;
+    while cursor < bytes.len() {
+        match encoding.decode(&bytes[cursor..]) {
+            Unit::Char(ch, _) if tokenizer.starts_word(encoding, ch) => {
+                match word(encoding, tokenizer, bytes, cursor, eof, false) {
+                    Run::Word(word) => {
+                        cursor = word.range.end;
+                        words.push(word);
+                    }
+                    Run::Misaligned => cursor += 1,
+                    Run::NeedMore => return (words, cursor),
+                }
+            }
+            // Wider encodings step a single byte past anything that is not a word, so text
+            // sitting on an odd offset inside binary data is still found. ASCII other than NUL
+            // is stepped over whole, the byte after it can never start a word.
+            Unit::Char(ch, len) if encoding.unit_len() == 1 || (ch.is_ascii() && ch != '\0') => {
+                cursor += len
+            }
+            Unit::Incomplete if !eof => return (words, cursor),
+            _ => cursor += 1,
+        }
+    }
+
+    (words, cursor)
+}
+
+/// Like `words` on complete input, but only reading code units where `alignment` allows.
+pub(crate) fn aligned_words(
+    encoding: Encoding,
+    tokenizer: &Tokenizer,
+    bytes: &[u8],
+    alignment: Alignment,
+) -> Vec<Word> {
+    let base = match alignment {
+        Alignment::Any => return words(encoding, tokenizer, bytes, true).0,
+        Alignment::From(base) => base,
+        Alignment::Auto => text_alignment(encoding, bytes),
+    };
+
+    let mut words = vec![];
+    let mut cursor = base;
+    while cursor < bytes.len() {
+        match encoding.decode(&bytes[cursor..]) {
+            Unit::Char(ch, len) if tokenizer.starts_word(encoding, ch) => {
+                match word(encoding, tokenizer, bytes, cursor, true, true) {
+                    Run::Word(word) => {
+                        cursor = word.range.end;
+                        words.push(word);
+                    }
+                    _ => cursor += len,
+                }
+            }
+            Unit::Char(_, len) => cursor += len,
+            _ => cursor += encoding.unit_len(),
+        }
+    }
+
+    words
+}
+
+// The offset below the unit length whose units read as the most letters, digits and spaces.
+// Text on the wrong alignment reads as ASCII shifted into the high half, which is not counted.
+// A tie goes to the lowest offset.
+fn text_alignment(encoding: Encoding, bytes: &[u8]) -> usize {
+    let step = encoding.unit_len();
+    (0..step)
+        .map(|base| {
+            let score = (base..bytes.len())
+                .step_by(step)
+                .filter(|&at| match encoding.decode(&bytes[at..]) {
+                    Unit::Char(ch, _) => {
+                        (ch.is_alphanumeric() || ch.is_whitespace()) && !is_shifted_ascii(ch)
+                    }
+                    _ => false,
+                })
+                .count();
+            (base, score)
+        })
+        .rev()
+        .max_by_key(|&(_, score)| score)
+        .map_or(0, |(base, _)| base)
+}
+
+fn word(
+    encoding: Encoding,
+    tokenizer: &Tokenizer,
+    bytes: &[u8],
+    start: usize,
+    eof: bool,
+    aligned: bool,
+) -> Run {
+    let wide = encoding.unit_len() > 1;
+    let mut chars: Vec<(char, usize)> = vec![];
+    let mut cursor = start;
+    let truncated = loop {
+        match encoding.decode(&bytes[cursor..]) {
+            Unit::Char(ch, len) if chars.is_empty() || tokenizer.continues_word(ch) => {
+                // Stepping one byte at a time can land in the middle of ASCII text, where every
+                // unit reads as an ASCII byte shifted into the high half. Those runs are skipped,
+                // unless the word so far is plain ASCII and simply ends there.
+                if wide && !aligned && is_shifted_ascii(ch) {
+                    if chars.iter().all(|(c, _)| c.is_ascii()) && !chars.is_empty() {
+                        break false;
+                    }
+
+                    return Run::Misaligned;
+                }
+
+                cursor += len;
+                chars.push((ch, cursor));
+            }
+            Unit::Char(ch, len) if tokenizer.joins(ch) => {
+                match encoding.decode(&bytes[cursor + len..]) {
+                    Unit::Char(next, _) if tokenizer.continues_word(next) => {
+                        cursor += len;
+                        chars.push((ch, cursor));
+                    }
+                    Unit::Incomplete if !eof => return Run::NeedMore,
+                    _ => break false,
+                }
+            }
+            Unit::Incomplete if !eof => return Run::NeedMore,
+            Unit::Incomplete => break cursor < bytes.len(),
+            _ => break false,
+        }
+    };
+
+    // A dangling byte at the end of the input means the bytes before it are not all text, so a
+    // non-ASCII unit right in front of it is left out of the word.
+    if wide && truncated && chars.last().is_some_and(|(c, _)| !c.is_ascii()) {
+        chars.pop();
+    }
+
+    // A joiner is only kept when the word goes on after it.
+    while chars
+        .last()
+        .is_some_and(|&(c, _)| tokenizer.joins(c) && !tokenizer.continues_word(c))
+    {
+        chars.pop();
+    }
+
+    match chars.last() {
+        Some(&(_, end)) => Run::Word(Word {
+            text: chars.iter().map(|(c, _)| c).collect(),
+            range: start..end,
+        }),
+        None => Run::Misaligned,
+    }
+}
+
+fn is_shifted_ascii(ch: char) -> bool {
+    let value = ch as u32;
+    value & 0xFF == 0 && (0x100..0x8000).contains(&value)
+}
+
+/// Writes `bytes` with every edit applied, handing each edit to `replaced` together with the
+/// range its replacement took up in the output.
+pub(crate) fn splice(
+    encoding: Encoding,
+    bytes: &[u8],
+    edits: &[Edit],
+    mut replaced: impl FnMut(&Edit, Range<usize>),
+) -> Vec<u8> {
+    let mut new_bytes: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut cursor = 0;
+    for edit in edits {
+        new_bytes.extend_from_slice(&bytes[cursor..edit.range.start]);
+        let start = new_bytes.len();
+        new_bytes.extend(encoding.encode(&edit.value));
+        replaced(edit, start..new_bytes.len());
+        cursor = edit.range.end;
+    }
+
+    new_bytes.extend_from_slice(&bytes[cursor..]);
+    new_bytes
+}
@@ -0,0 +1,318 @@
+use crate::encoding::Encoding;
+use crate::error::ReplaceError;
+use crate::replacer::{Match, Replacer};
+use crate::scanner::{self, Word};
+use std::io::{ErrorKind, Read, Write};
+
+pub(crate) const CHUNK_SIZE: usize = 64 * 1024;
+
+// How far back from the end of the buffer words may be held while waiting for a phrase to go on.
+// A phrase that only continues after a longer run of spaces is not matched.
+const MAX_HELD_BACK: usize = 4 * CHUNK_SIZE;
+
+pub(crate) fn replace(
+    replacer: &Replacer,
+    encoding: Encoding,
+    mut reader: impl Read,
+    mut writer: impl Write,
+    chunk_size: usize,
+    mut report: Option<&mut Vec<Match>>,
+) -> Result<u128, ReplaceError> {
+    let mut buffer: Vec<u8> = Vec::with_capacity(chunk_size * 2);
+    let mut chunk = vec![0; chunk_size];
+    let mut words: Vec<Word> = vec![];
+    let mut scanned = 0;
+    let mut count = 0;
+    let mut read_total = 0;
+    let mut written_total = 0;
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(read) => read,
+            Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error.into()),
+        };
+
+        let eof = read == 0;
+        buffer.extend_from_slice(&chunk[..read]);
+
+        // Scanning picks up where the last chunk left it, only the part of the buffer that later
+        // bytes can no longer change is written out.
+        let (found, resume) =
+            scanner::words(encoding, replacer.tokenizer(), &buffer[scanned..], eof);
+        words.extend(found.into_iter().map(|word| Word {
+            range: word.range.start + scanned..word.range.end + scanned,
+            text: word.text,
+        }));
+        let resume = scanned + resume;
+
+        let gap = |range| encoding.decode_str(&buffer[range]);
+        let (mut edits, mut next) = replacer.edits(&words, gap, eof);
+        // Held back words are settled once no phrase can go on past the last of them.
+        if let (Some(held), Some(last)) = (words.get(next), words.last())
+            && (buffer.len() - held.range.start > MAX_HELD_BACK
+                || gap(last.range.end..resume).is_none())
+        {
+            (edits, next) = replacer.edits(&words, gap, true);
+        }
+        let end = words.get(next).map_or(resume, |word| word.range.start);
+
+        count += edits.len() as u128;
+        let new_bytes = scanner::splice(encoding, &buffer[..end], &edits, |edit, output| {
+            if let Some(matches) = report.as_mut() {
+                let output = output.start + written_total..output.end + written_total;
+                matches.push(edit.to_match(read_total, output));
+            }
+        });
+
+        writer.write_all(&new_bytes)?;
+        read_total += end;
+        written_total += new_bytes.len();
+        buffer.drain(..end);
+        words.drain(..next);
+        for word in &mut words {
+            word.range = word.range.start - end..word.range.end - end;
+        }
+        scanned = resume - end;
+
+        if eof {
+            break;
+        }
+    }
+
+    writer.flush()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use std::cell::Cell;
+    use std::io::{self, Read, Write};
+
+    use crate::encoding::Encoding;
+    use crate::error::ReplaceError;
+    use crate::replacer::Replacer;
+    use crate::stream::{CHUNK_SIZE, MAX_HELD_BACK, replace};
+    use crate::tokenizer::Tokenizer;
+    use crate::utils::{get_be_16_bytes, get_be_32_bytes, get_le_16_bytes, get_le_32_bytes};
+
+    #[test]
+    fn streams_text() {
+        let replacer = Replacer::new(&create_map()).unwrap();
+        let mut output = vec![];
+        let count = replacer
+            .replace_stream(Encoding::Utf8, "First and another.".as_bytes(), &mut output)
+            .unwrap();
+        assert_eq!("Changed and something.".as_bytes().to_vec(), output);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn words_split_across_chunks() {
+        let content = "first and another, anothers. Sign in to new york city";
+        let expected = "changed and something, somethingelse. Log in to the big apple";
+        for chunk_size in 1..10 {
+            let (count, output) = stream(Encoding::Ascii, content.as_bytes(), chunk_size);
+            assert_eq!(expected.as_bytes().to_vec(), output);
+            assert_eq!(count, 5);
+        }
+    }
+
+    #[test]
+    fn utf8_chars_split_across_chunks() {
+        let content = "Русский first 中文 another";
+        let expected = "Русский changed 中文 something";
+        for chunk_size in 1..10 {
+            let (count, output) = stream(Encoding::Utf8, content.as_bytes(), chunk_size);
+            assert_eq!(expected.as_bytes().to_vec(), output);
+            assert_eq!(count, 2);
+        }
+    }
+
+    #[test]
+    fn utf16_units_split_across_chunks() {
+        let content = "\nfirst and \nAnother, \nANOTHERS. sign in";
+        let expected = "\nchanged and \nSomething, \nSOMETHINGELSE. log in";
+        for chunk_size in 1..10 {
+            let (count, output) = stream(Encoding::Utf16Le, &get_le_16_bytes(content), chunk_size);
+            assert_eq!(get_le_16_bytes(expected), output);
+            assert_eq!(count, 4);
+
+            let (count, output) = stream(Encoding::Utf16Be, &get_be_16_bytes(content), chunk_size);
+            assert_eq!(get_be_16_bytes(expected), output);
+            assert_eq!(count, 4);
+        }
+    }
+
+    #[test]
+    fn utf32_units_split_across_chunks() {
+        let content = "first and Another, sign in";
+        let expected = "changed and Something, log in";
+        for chunk_size in 1..10 {
+            let (count, output) = stream(Encoding::Utf32Le, &get_le_32_bytes(content), chunk_size);
+            assert_eq!(get_le_32_bytes(expected), output);
+            assert_eq!(count, 3);
+
+            let (count, output) = stream(Encoding::Utf32Be, &get_be_32_bytes(content), chunk_size);
+            assert_eq!(get_be_32_bytes(expected), output);
+            assert_eq!(count, 3);
+        }
+    }
+
+    #[test]
+    fn matches_in_memory_replacement_with_binary_data() {
+        let replacer = Replacer::new(&create_map()).unwrap();
+        let content = [
+            vec![22, 1, 255],
+            get_le_16_bytes("small first"),
+            vec![2],
+            get_le_16_bytes(" and another"),
+            vec![255, 249, 10],
+        ]
+        .concat();
+
+        let (expected_count, expected) = replacer.replace_le_16(&content);
+        for chunk_size in 1..10 {
+            let (count, output) = stream(Encoding::Utf16Le, &content, chunk_size);
+            assert_eq!(expected, output);
+            assert_eq!(expected_count, count);
+        }
+    }
+
+    #[test]
+    fn joined_words_split_across_chunks() {
+        let mut map = create_map();
+        map.insert("don't".into(), "do not".into());
+        let replacer = Replacer::builder()
+            .tokenizer(Tokenizer::new().join_chars(&['\'']))
+            .build(&map)
+            .unwrap();
+        let content = get_le_16_bytes("don't first' another'");
+        let expected = get_le_16_bytes("do not changed' something'");
+        for chunk_size in 1..10 {
+            let mut output = vec![];
+            let count = replace(
+                &replacer,
+                Encoding::Utf16Le,
+                content.as_slice(),
+                &mut output,
+                chunk_size,
+                None,
+            )
+            .unwrap();
+            assert_eq!(expected, output);
+            assert_eq!(count, 3);
+        }
+    }
+
+    #[test]
+    fn reports_offsets_across_chunks() {
+        let replacer = Replacer::new(&create_map()).unwrap();
+        let content = get_le_16_bytes("small first, and sign in");
+        let (expected, _) = replacer.replace_with_report(Encoding::Utf16Le, &content);
+        for chunk_size in 1..10 {
+            let mut matches = vec![];
+            let mut output = vec![];
+            replace(
+                &replacer,
+                Encoding::Utf16Le,
+                content.as_slice(),
+                &mut output,
+                chunk_size,
+                Some(&mut matches),
+            )
+            .unwrap();
+            assert_eq!(expected, matches);
+        }
+    }
+
+    #[test]
+    fn sparse_input_is_not_held_back() {
+        struct Counting<'a>(&'a [u8], &'a Cell<usize>);
+        impl Read for Counting<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let read = self.0.read(buf)?;
+                self.1.set(self.1.get() + read);
+                Ok(read)
+            }
+        }
+
+        struct Lagging<'a>(&'a Cell<usize>, usize, usize);
+        impl Write for Lagging<'_> {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.1 += buf.len();
+                self.2 = self.2.max(self.0.get().saturating_sub(self.1));
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let replacer = Replacer::new(&create_map()).unwrap();
+        for encoding in [Encoding::Utf8, Encoding::Utf16Le] {
+            let content = [
+                encoding.encode("sign "),
+                vec![0; 16 * 1024 * 1024],
+                encoding.encode(" in first"),
+            ]
+            .concat();
+            let read = Cell::new(0);
+            let mut output = Lagging(&read, 0, 0);
+            let count = replace(
+                &replacer,
+                encoding,
+                Counting(&content, &read),
+                &mut output,
+                CHUNK_SIZE,
+                None,
+            )
+            .unwrap();
+            let grown = encoding.encode("changed").len() - encoding.encode("first").len();
+            assert_eq!(count, 1);
+            assert_eq!(content.len() + grown, output.1);
+            assert!(output.2 <= MAX_HELD_BACK + CHUNK_SIZE, "{}", output.2);
+        }
+    }
+
+    #[test]
+    fn reports_io_errors() {
+        struct Broken;
+        impl Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("broken"))
+            }
+        }
+
+        let replacer = Replacer::new(&create_map()).unwrap();
+        let result = replacer.replace_stream(Encoding::Utf8, Broken, vec![]);
+        assert!(matches!(result, Err(ReplaceError::Io(_))));
+    }
+
+    #[test]
+    fn empty_input() {
+        let (count, output) = stream(Encoding::Utf16Le, &[], 4);
+        assert!(output.is_empty());
+        assert_eq!(count, 0);
+    }
+
+    fn stream(encoding: Encoding, content: &[u8], chunk_size: usize) -> (u128, Vec<u8>) {
+        let replacer = Replacer::new(&create_map()).unwrap();
+        let mut output = vec![];
+        let count = replace(&replacer, encoding, content, &mut output, chunk_size, None).unwrap();
+        (count, output)
+    }
+
+    fn create_map() -> HashMap<String, String> {
+        let mut map = HashMap::new();
+        map.insert("first".into(), "changed".into());
+        map.insert("another".into(), "something".into());
+        map.insert("anothers".into(), "somethingelse".into());
+        map.insert("small".into(), "this is bigger than the original".into());
+        map.insert("sign in".into(), "log in".into());
+        map.insert("new york city".into(), "the big apple".into());
+        map
+    }
+}
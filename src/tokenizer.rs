@@ -0,0 +1,133 @@
+use crate::encoding::Encoding;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CharClass {
+    Alphabetic,
+    Alphanumeric,
+    AsciiAlphanumeric,
+}
+
+impl CharClass {
+    fn contains(self, ch: char) -> bool {
+        match self {
+            CharClass::Alphabetic => ch.is_alphabetic(),
+            CharClass::Alphanumeric => ch.is_alphanumeric(),
+            CharClass::AsciiAlphanumeric => ch.is_ascii_alphanumeric(),
+        }
+    }
+}
+
+/// Decides which characters make up a word. Joiners only join when a word character follows,
+/// so with '\'' as a joiner "don't" is one word while "'quoted'" keeps its quotes outside.
+/// Unless set, words start on letters in UTF-8 and on letters and digits in other encodings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tokenizer {
+    start: Option<CharClass>,
+    body: CharClass,
+    extra: Vec<char>,
+    joiners: Vec<char>,
+}
+
+impl Default for Tokenizer {
+    fn default() -> Self {
+        Self {
+            start: None,
+            body: CharClass::Alphanumeric,
+            extra: vec!['_'],
+            joiners: vec![],
+        }
+    }
+}
+
+impl Tokenizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn starts_on(mut self, class: CharClass) -> Self {
+        self.start = Some(class);
+        self
+    }
+
+    pub fn continues_on(mut self, class: CharClass) -> Self {
+        self.body = class;
+        self
+    }
+
+    pub fn extra_chars(mut self, chars: &[char]) -> Self {
+        self.extra = chars.to_vec();
+        self
+    }
+
+    pub fn join_chars(mut self, chars: &[char]) -> Self {
+        self.joiners = chars.to_vec();
+        self
+    }
+
+    pub(crate) fn starts_word(&self, encoding: Encoding, ch: char) -> bool {
+        let start = self.start.unwrap_or(match encoding {
+            Encoding::Utf8 => CharClass::Alphabetic,
+            _ => CharClass::Alphanumeric,
+        });
+        start.contains(ch)
+    }
+
+    pub(crate) fn continues_word(&self, ch: char) -> bool {
+        self.body.contains(ch) || self.extra.contains(&ch)
+    }
+
+    pub(crate) fn joins(&self, ch: char) -> bool {
+        self.joiners.contains(&ch)
+    }
+
+    // Without a start class, keys only have to start a word in one of the encodings.
+    pub(crate) fn is_valid_key(&self, key: &str) -> bool {
+        let start = self.start.unwrap_or(CharClass::Alphanumeric);
+        key.starts_with(|c| start.contains(c)) && key.ends_with(|c| self.continues_word(c))
+    }
+
+    /// The most words `text` can be split into, counting anything joined as one.
+    pub(crate) fn count_words(&self, text: &str) -> usize {
+        text.split(|c: char| !self.continues_word(c) && !self.joins(c))
+            .filter(|word| !word.is_empty())
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encoding::Encoding;
+    use crate::tokenizer::{CharClass, Tokenizer};
+
+    #[test]
+    fn default_rules() {
+        let tokenizer = Tokenizer::new();
+        assert!(tokenizer.starts_word(Encoding::Ascii, '2'));
+        assert!(tokenizer.starts_word(Encoding::Utf16Le, '2'));
+        assert!(!tokenizer.starts_word(Encoding::Utf8, '2'));
+        assert!(tokenizer.starts_word(Encoding::Utf8, 'é'));
+        assert!(!tokenizer.starts_word(Encoding::Ascii, '_'));
+        assert!(tokenizer.continues_word('_'));
+        assert!(!tokenizer.joins('-'));
+    }
+
+    #[test]
+    fn configured_rules() {
+        let tokenizer = Tokenizer::new()
+            .starts_on(CharClass::Alphabetic)
+            .continues_on(CharClass::AsciiAlphanumeric)
+            .extra_chars(&[])
+            .join_chars(&['-', '\'']);
+        assert!(!tokenizer.starts_word(Encoding::Ascii, '2'));
+        assert!(!tokenizer.continues_word('é'));
+        assert!(!tokenizer.continues_word('_'));
+        assert!(tokenizer.joins('\''));
+    }
+
+    #[test]
+    fn counts_joined_words_once() {
+        let tokenizer = Tokenizer::new().join_chars(&['-']);
+        assert_eq!(tokenizer.count_words("e-mail address"), 2);
+        assert_eq!(Tokenizer::new().count_words("e-mail address"), 3);
+    }
+}
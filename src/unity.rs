@@ -0,0 +1,803 @@
+use crate::encoding::Encoding;
+use crate::error::ReplaceError;
+use crate::replacer::Replacer;
+
+const ALIGN_BYTES: u32 = 0x4000;
+const IS_ARRAY: u8 = 0x1;
+const COMMON_STRING: u32 = 0x8000_0000;
+const MONO_BEHAVIOUR: i32 = 114;
+const OBJECT_ALIGNMENT: usize = 8;
+
+// Type tree names with this bit set are offsets into a table built into Unity.
+const COMMON_STRINGS: &str = concat!(
+    "AABB\0AnimationClip\0AnimationCurve\0AnimationState\0Array\0Base\0BitField\0bitset\0bool\0",
+    "char\0ColorRGBA\0Component\0data\0deque\0double\0dynamic_array\0FastPropertyName\0first\0",
+    "float\0Font\0GameObject\0Generic Mono\0GradientNEW\0GUID\0GUIStyle\0int\0list\0long long\0",
+    "map\0Matrix4x4f\0MdFour\0MonoBehaviour\0MonoScript\0m_ByteSize\0m_Curve\0",
+    "m_EditorClassIdentifier\0m_EditorHideFlags\0m_Enabled\0m_ExtensionPtr\0m_GameObject\0",
+    "m_Index\0m_IsArray\0m_IsStatic\0m_MetaFlag\0m_Name\0m_ObjectHideFlags\0m_PrefabInternal\0",
+    "m_PrefabParentObject\0m_Script\0m_StaticEditorFlags\0m_Type\0m_Version\0Object\0pair\0",
+    "PPtr<Component>\0PPtr<GameObject>\0PPtr<Material>\0PPtr<MonoBehaviour>\0PPtr<MonoScript>\0",
+    "PPtr<Object>\0PPtr<Prefab>\0PPtr<Sprite>\0PPtr<TextAsset>\0PPtr<Texture>\0PPtr<Texture2D>\0",
+    "PPtr<Transform>\0Prefab\0Quaternionf\0Rectf\0RectInt\0RectOffset\0second\0set\0short\0size\0",
+    "SInt16\0SInt32\0SInt64\0SInt8\0staticvector\0string\0TextAsset\0TextMesh\0Texture\0",
+    "Texture2D\0Transform\0TypelessData\0UInt16\0UInt32\0UInt64\0UInt8\0unsigned int\0",
+    "unsigned long long\0unsigned short\0vector\0Vector2f\0Vector3f\0Vector4f\0",
+    "m_ScriptingClassIdentifier\0Gradient\0Type*\0int2_storage\0int3_storage\0BoundsInt\0",
+    "m_CorrespondingSourceObject\0m_PrefabInstance\0m_PrefabAsset\0FileSize\0Hash128\0",
+);
+
+struct Node {
+    level: u8,
+    flags: u8,
+    type_name: String,
+    byte_size: i32,
+    meta_flags: u32,
+}
+
+struct Type {
+    class_id: i32,
+    nodes: Vec<Node>,
+}
+
+struct Object {
+    start: usize,
+    size: usize,
+    // Where the start and size are written in the metadata, so they can be patched.
+    start_field: usize,
+    size_field: usize,
+    type_index: usize,
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ReplaceError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len());
+        let Some(end) = end else {
+            return Err(invalid("unexpected end of data"));
+        };
+
+        let taken = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], ReplaceError> {
+        let mut array: [u8; N] = self.take(N)?.try_into().unwrap();
+        if !self.big_endian {
+            array.reverse();
+        }
+
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> Result<u8, ReplaceError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, ReplaceError> {
+        self.array().map(u32::from_be_bytes)
+    }
+
+    fn i32(&mut self) -> Result<i32, ReplaceError> {
+        self.array().map(i32::from_be_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64, ReplaceError> {
+        self.array().map(u64::from_be_bytes)
+    }
+
+    fn count(&mut self) -> Result<usize, ReplaceError> {
+        let count = self.i32()?;
+        usize::try_from(count).map_err(|_| invalid(&format!("negative length {count}")))
+    }
+
+    fn skip(&mut self, len: usize) -> Result<(), ReplaceError> {
+        self.take(len).map(|_| ())
+    }
+
+    fn skip_string(&mut self) -> Result<(), ReplaceError> {
+        let rest = &self.bytes[self.pos.min(self.bytes.len())..];
+        let len = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(invalid("unterminated string"))?;
+        self.skip(len + 1)
+    }
+
+    fn align(&mut self) {
+        self.pos = align(self.pos, 4);
+    }
+}
+
+/// Replaces words inside the strings of a Unity SerializedFile. Strings are stored as a length
+/// followed by UTF-8 bytes padded to 4 bytes, so every object is walked along its type tree and
+/// written again, moving the objects after it and updating their offsets and sizes.
+pub(crate) fn replace(replacer: &Replacer, bytes: &[u8]) -> Result<(u128, Vec<u8>), ReplaceError> {
+    let mut header = Reader {
+        bytes,
+        pos: 0,
+        big_endian: true,
+    };
+    header.skip(4)?;
+    let mut file_size = u64::from(header.u32()?);
+    let version = header.u32()?;
+    let mut data_offset = u64::from(header.u32()?);
+    if !(14..=22).contains(&version) {
+        return Err(invalid(&format!(
+            "unsupported SerializedFile version {version}"
+        )));
+    }
+
+    let big_endian = header.u8()? != 0;
+    header.skip(3)?;
+    if version >= 22 {
+        header.skip(4)?;
+        file_size = header.u64()?;
+        data_offset = header.u64()?;
+        header.skip(8)?;
+    }
+
+    if file_size != bytes.len() as u64 {
+        return Err(invalid("the file size does not match the header"));
+    }
+
+    let data_offset = usize::try_from(data_offset)
+        .ok()
+        .filter(|&offset| offset <= bytes.len())
+        .ok_or(invalid("the data offset is past the end of the file"))?;
+
+    let mut metadata = Reader {
+        bytes,
+        pos: header.pos,
+        big_endian,
+    };
+    let (types, objects) = read_metadata(&mut metadata, version, data_offset)?;
+    // The start and size fields patched below are all in front of where the metadata ends.
+    if metadata.pos > data_offset {
+        return Err(invalid("the metadata runs past the data offset"));
+    }
+
+    let mut total = 0;
+    let mut written = Vec::with_capacity(objects.len());
+    for object in &objects {
+        let mut rewrite = Rewrite {
+            nodes: &types[object.type_index].nodes,
+            input: Reader {
+                bytes: &bytes[object.start..object.start + object.size],
+                pos: 0,
+                big_endian,
+            },
+            output: Vec::with_capacity(object.size),
+            replacer,
+            count: 0,
+        };
+        rewrite.node(0)?;
+        if rewrite.input.pos != object.size {
+            return Err(invalid(&format!(
+                "the object at {} does not match its type tree",
+                object.start
+            )));
+        }
+
+        total += rewrite.count;
+        written.push(rewrite.output);
+    }
+
+    if total == 0 {
+        return Ok((0, bytes.to_vec()));
+    }
+
+    let mut output = bytes[..data_offset].to_vec();
+    let mut order: Vec<usize> = (0..objects.len()).collect();
+    order.sort_by_key(|&index| objects[index].start);
+    let mut end = data_offset;
+    for index in order {
+        let object = &objects[index];
+        output.resize(
+            data_offset + align(output.len() - data_offset, OBJECT_ALIGNMENT),
+            0,
+        );
+        let start = (output.len() - data_offset) as u64;
+        let size = u32::try_from(written[index].len()).map_err(|_| invalid("object too large"))?;
+        if version >= 22 {
+            patch(
+                &mut output,
+                object.start_field,
+                &start.to_be_bytes(),
+                big_endian,
+            );
+        } else {
+            let start = u32::try_from(start).map_err(|_| invalid("file too large"))?;
+            patch(
+                &mut output,
+                object.start_field,
+                &start.to_be_bytes(),
+                big_endian,
+            );
+        }
+
+        patch(
+            &mut output,
+            object.size_field,
+            &size.to_be_bytes(),
+            big_endian,
+        );
+        output.extend_from_slice(&written[index]);
+        end = end.max(object.start + object.size);
+    }
+
+    output.extend_from_slice(&bytes[end..]);
+    let file_size = output.len() as u64;
+    if version >= 22 {
+        patch(&mut output, 24, &file_size.to_be_bytes(), true);
+    } else {
+        let file_size = u32::try_from(file_size).map_err(|_| invalid("file too large"))?;
+        patch(&mut output, 4, &file_size.to_be_bytes(), true);
+    }
+
+    Ok((total, output))
+}
+
+fn read_metadata(
+    reader: &mut Reader,
+    version: u32,
+    data_offset: usize,
+) -> Result<(Vec<Type>, Vec<Object>), ReplaceError> {
+    reader.skip_string()?;
+    reader.skip(4)?;
+    if reader.u8()? == 0 {
+        return Err(invalid(
+            "the file has no type trees to find its strings with",
+        ));
+    }
+
+    let type_count = reader.count()?;
+    let types = (0..type_count)
+        .map(|_| read_type(reader, version))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let object_count = reader.count()?;
+    let mut objects = Vec::with_capacity(object_count.min(reader.bytes.len()));
+    for _ in 0..object_count {
+        reader.align();
+        reader.skip(8)?;
+        let start_field = reader.pos;
+        let start = match version >= 22 {
+            true => reader.u64()?,
+            false => u64::from(reader.u32()?),
+        };
+        let size_field = reader.pos;
+        let size = reader.u32()? as usize;
+        let type_id = reader.i32()?;
+        if version < 16 {
+            reader.skip(2)?;
+        }
+
+        if version < 17 {
+            reader.skip(2)?;
+        }
+
+        if version == 15 || version == 16 {
+            reader.skip(1)?;
+        }
+
+        let type_index = match version >= 16 {
+            true => usize::try_from(type_id)
+                .ok()
+                .filter(|&index| index < types.len()),
+            false => types.iter().position(|t| t.class_id == type_id),
+        }
+        .ok_or(invalid(&format!("unknown type {type_id}")))?;
+
+        let start = usize::try_from(start)
+            .ok()
+            .and_then(|start| start.checked_add(data_offset))
+            .filter(|start| {
+                start
+                    .checked_add(size)
+                    .is_some_and(|end| end <= reader.bytes.len())
+            })
+            .ok_or(invalid("an object is past the end of the file"))?;
+
+        objects.push(Object {
+            start,
+            size,
+            start_field,
+            size_field,
+            type_index,
+        });
+    }
+
+    Ok((types, objects))
+}
+
+fn read_type(reader: &mut Reader, version: u32) -> Result<Type, ReplaceError> {
+    let class_id = reader.i32()?;
+    if version >= 16 {
+        reader.skip(1)?;
+    }
+
+    if version >= 17 {
+        reader.skip(2)?;
+    }
+
+    let script = match version >= 16 {
+        true => class_id == MONO_BEHAVIOUR,
+        false => class_id < 0,
+    };
+    if script {
+        reader.skip(16)?;
+    }
+
+    reader.skip(16)?;
+
+    let node_count = reader.count()?;
+    let buffer_size = reader.count()?;
+    let mut raw = Vec::with_capacity(node_count.min(reader.bytes.len()));
+    for _ in 0..node_count {
+        reader.skip(2)?;
+        let level = reader.u8()?;
+        let flags = reader.u8()?;
+        let type_offset = reader.u32()?;
+        reader.skip(4)?;
+        let byte_size = reader.i32()?;
+        reader.skip(4)?;
+        let meta_flags = reader.u32()?;
+        if version >= 19 {
+            reader.skip(8)?;
+        }
+
+        raw.push((level, flags, type_offset, byte_size, meta_flags));
+    }
+
+    let buffer = reader.take(buffer_size)?;
+    let nodes = raw
+        .into_iter()
+        .map(|(level, flags, type_offset, byte_size, meta_flags)| {
+            Ok(Node {
+                level,
+                flags,
+                type_name: type_name(buffer, type_offset)?,
+                byte_size,
+                meta_flags,
+            })
+        })
+        .collect::<Result<Vec<_>, ReplaceError>>()?;
+
+    if nodes.is_empty() {
+        return Err(invalid(&format!("empty type tree for class {class_id}")));
+    }
+
+    if version >= 21 {
+        let dependencies = reader.count()?;
+        reader.skip(dependencies.saturating_mul(4))?;
+    }
+
+    Ok(Type { class_id, nodes })
+}
+
+fn type_name(buffer: &[u8], offset: u32) -> Result<String, ReplaceError> {
+    let (strings, offset) = match offset & COMMON_STRING {
+        0 => (buffer, offset as usize),
+        _ => (
+            COMMON_STRINGS.as_bytes(),
+            (offset & !COMMON_STRING) as usize,
+        ),
+    };
+
+    let name = strings
+        .get(offset..)
+        .and_then(|rest| rest.split(|&b| b == 0).next())
+        .ok_or(invalid(&format!(
+            "type name offset {offset} is out of range"
+        )))?;
+    Ok(String::from_utf8_lossy(name).into_owned())
+}
+
+struct Rewrite<'a> {
+    nodes: &'a [Node],
+    input: Reader<'a>,
+    output: Vec<u8>,
+    replacer: &'a Replacer,
+    count: u128,
+}
+
+impl Rewrite<'_> {
+    fn node(&mut self, index: usize) -> Result<(), ReplaceError> {
+        let node = &self.nodes[index];
+        let children = self.children(index);
+        if node.type_name == "string" {
+            let len = self.input.count()?;
+            let text = self.input.take(len)?;
+            let (count, new_text) = self.replacer.replace(Encoding::Utf8, text);
+            let len = i32::try_from(new_text.len()).map_err(|_| invalid("string too long"))?;
+            self.write(&len.to_be_bytes());
+            self.output.extend_from_slice(&new_text);
+            self.count += count;
+            // The alignment of a string sits on the array of characters inside it.
+            if children
+                .first()
+                .is_some_and(|&child| self.nodes[child].meta_flags & ALIGN_BYTES != 0)
+            {
+                self.align();
+            }
+        } else if node.type_name == "ManagedReferencesRegistry" {
+            return Err(invalid("[SerializeReference] fields are not supported"));
+        } else if node.flags & IS_ARRAY != 0 {
+            let &[size, element] = children.as_slice() else {
+                return Err(invalid("an array without a size and element type"));
+            };
+
+            if self.nodes[size].byte_size != 4 {
+                return Err(invalid("an array size that is not an int"));
+            }
+
+            let len = self.input.count()?;
+            self.write(&(len as i32).to_be_bytes());
+            let fixed = self.nodes[element].byte_size;
+            if self.children(element).is_empty() && fixed > 0 {
+                self.copy(
+                    len.checked_mul(fixed as usize)
+                        .ok_or(invalid("array too large"))?,
+                )?;
+            } else {
+                for _ in 0..len {
+                    self.node(element)?;
+                }
+            }
+        } else if children.is_empty() {
+            let size = usize::try_from(node.byte_size)
+                .map_err(|_| invalid(&format!("{} has no size", node.type_name)))?;
+            self.copy(size)?;
+        } else {
+            for child in children {
+                self.node(child)?;
+            }
+        }
+
+        if node.meta_flags & ALIGN_BYTES != 0 {
+            self.align();
+        }
+
+        Ok(())
+    }
+
+    fn children(&self, index: usize) -> Vec<usize> {
+        let level = self.nodes[index].level;
+        (index + 1..self.nodes.len())
+            .take_while(|&child| self.nodes[child].level > level)
+            .filter(|&child| self.nodes[child].level == level + 1)
+            .collect()
+    }
+
+    fn copy(&mut self, len: usize) -> Result<(), ReplaceError> {
+        let bytes = self.input.take(len)?;
+        self.output.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    // Takes big endian bytes and writes them in the order of the file.
+    fn write(&mut self, bytes: &[u8]) {
+        let start = self.output.len();
+        self.output.extend_from_slice(bytes);
+        if !self.input.big_endian {
+            self.output[start..].reverse();
+        }
+    }
+
+    fn align(&mut self) {
+        self.input.align();
+        self.output.resize(align(self.output.len(), 4), 0);
+    }
+}
+
+fn patch(bytes: &mut [u8], at: usize, value: &[u8], big_endian: bool) {
+    let field = &mut bytes[at..at + value.len()];
+    field.copy_from_slice(value);
+    if !big_endian {
+        field.reverse();
+    }
+}
+
+fn align(pos: usize, to: usize) -> usize {
+    pos.next_multiple_of(to)
+}
+
+fn invalid(message: &str) -> ReplaceError {
+    ReplaceError::InvalidAsset(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::error::ReplaceError;
+    use crate::replacer::Replacer;
+    use crate::unity::{COMMON_STRING, COMMON_STRINGS, replace};
+
+    const ALIGN: u32 = 0x4000;
+
+    // A localization table: m_Name, a list of (m_Id, m_Localized) entries, a flag and a count.
+    const NODES: [(u8, u8, &str, i32, u32); 16] = [
+        (0, 0, "MonoBehaviour", -1, 0),
+        (1, 0, "string", -1, 0x8000),
+        (2, 1, "Array", -1, ALIGN),
+        (3, 0, "int", 4, 0),
+        (3, 0, "char", 1, 0),
+        (1, 0, "vector", -1, 0),
+        (2, 1, "Array", -1, 0),
+        (3, 0, "int", 4, 0),
+        (3, 0, "TableEntryData", -1, 0),
+        (4, 0, "SInt64", 8, 0),
+        (4, 0, "string", -1, 0),
+        (5, 1, "Array", -1, ALIGN),
+        (6, 0, "int", 4, 0),
+        (6, 0, "char", 1, 0),
+        (1, 0, "UInt8", 1, ALIGN),
+        (1, 0, "int", 4, 0),
+    ];
+
+    struct Writer {
+        bytes: Vec<u8>,
+        big_endian: bool,
+    }
+
+    impl Writer {
+        fn raw(&mut self, bytes: &[u8]) {
+            let start = self.bytes.len();
+            self.bytes.extend_from_slice(bytes);
+            if !self.big_endian {
+                self.bytes[start..].reverse();
+            }
+        }
+
+        fn string(&mut self, text: &str) {
+            self.raw(&(text.len() as i32).to_be_bytes());
+            self.bytes.extend_from_slice(text.as_bytes());
+            self.bytes.resize(self.bytes.len().next_multiple_of(4), 0);
+        }
+    }
+
+    fn table(name: &str, entries: &[(i64, &str)], big_endian: bool) -> Vec<u8> {
+        let mut writer = Writer {
+            bytes: vec![],
+            big_endian,
+        };
+        writer.string(name);
+        writer.raw(&(entries.len() as i32).to_be_bytes());
+        for (id, text) in entries {
+            writer.raw(&id.to_be_bytes());
+            writer.string(text);
+        }
+
+        writer.bytes.push(1);
+        writer
+            .bytes
+            .resize(writer.bytes.len().next_multiple_of(4), 0);
+        writer.raw(&7i32.to_be_bytes());
+        writer.bytes
+    }
+
+    fn common(type_name: &str) -> Option<u32> {
+        let mut offset = 0;
+        for name in COMMON_STRINGS.split('\0') {
+            if name == type_name {
+                return Some(offset);
+            }
+
+            offset += name.len() as u32 + 1;
+        }
+
+        None
+    }
+
+    fn asset(version: u32, big_endian: bool, objects: &[Vec<u8>]) -> Vec<u8> {
+        let mut starts = vec![];
+        let mut end: usize = 0;
+        for object in objects {
+            let start = end.next_multiple_of(8);
+            starts.push(start);
+            end = start + object.len();
+        }
+
+        let header_len = if version >= 22 { 48 } else { 20 };
+        let mut metadata = Writer {
+            bytes: vec![0; header_len],
+            big_endian,
+        };
+        metadata.bytes.extend_from_slice(b"2021.3.0f1\0");
+        metadata.raw(&19i32.to_be_bytes());
+        metadata.bytes.push(1);
+        metadata.raw(&1i32.to_be_bytes());
+        metadata.raw(&114i32.to_be_bytes());
+        metadata.bytes.push(0);
+        metadata.raw(&0i16.to_be_bytes());
+        metadata.bytes.extend_from_slice(&[0xAB; 32]);
+
+        let mut buffer = vec![];
+        metadata.raw(&(NODES.len() as i32).to_be_bytes());
+        let mut nodes = Writer {
+            bytes: vec![],
+            big_endian,
+        };
+        for (level, flags, type_name, byte_size, meta_flags) in NODES {
+            let type_offset = match common(type_name) {
+                Some(offset) => COMMON_STRING | offset,
+                None => {
+                    buffer.extend_from_slice(type_name.as_bytes());
+                    buffer.push(0);
+                    (buffer.len() - type_name.len() - 1) as u32
+                }
+            };
+            nodes.raw(&1u16.to_be_bytes());
+            nodes.bytes.extend_from_slice(&[level, flags]);
+            nodes.raw(&type_offset.to_be_bytes());
+            nodes.raw(&0u32.to_be_bytes());
+            nodes.raw(&byte_size.to_be_bytes());
+            nodes.raw(&0i32.to_be_bytes());
+            nodes.raw(&meta_flags.to_be_bytes());
+            if version >= 19 {
+                nodes.raw(&0u64.to_be_bytes());
+            }
+        }
+        metadata.raw(&(buffer.len() as i32).to_be_bytes());
+        metadata.bytes.extend_from_slice(&nodes.bytes);
+        metadata.bytes.extend_from_slice(&buffer);
+        if version >= 21 {
+            metadata.raw(&0i32.to_be_bytes());
+        }
+
+        metadata.raw(&(objects.len() as i32).to_be_bytes());
+        for (index, object) in objects.iter().enumerate() {
+            metadata
+                .bytes
+                .resize(metadata.bytes.len().next_multiple_of(4), 0);
+            metadata.raw(&(index as i64 + 1).to_be_bytes());
+            match version >= 22 {
+                true => metadata.raw(&(starts[index] as u64).to_be_bytes()),
+                false => metadata.raw(&(starts[index] as u32).to_be_bytes()),
+            }
+            metadata.raw(&(object.len() as u32).to_be_bytes());
+            metadata.raw(&0i32.to_be_bytes());
+        }
+        metadata.raw(&0i32.to_be_bytes());
+        metadata.raw(&0i32.to_be_bytes());
+
+        let metadata_size = metadata.bytes.len() - header_len;
+        let data_offset = metadata.bytes.len().next_multiple_of(16);
+        let mut bytes = metadata.bytes;
+        for (object, start) in objects.iter().zip(starts) {
+            bytes.resize(data_offset + start, 0);
+            bytes.extend_from_slice(object);
+        }
+
+        let endian = u8::from(big_endian);
+        let mut header = vec![];
+        if version >= 22 {
+            header.extend_from_slice(&[0; 8]);
+            header.extend_from_slice(&version.to_be_bytes());
+            header.extend_from_slice(&[0, 0, 0, 0, endian, 0, 0, 0]);
+            header.extend_from_slice(&(metadata_size as u32).to_be_bytes());
+            header.extend_from_slice(&(bytes.len() as u64).to_be_bytes());
+            header.extend_from_slice(&(data_offset as u64).to_be_bytes());
+            header.extend_from_slice(&[0; 8]);
+        } else {
+            header.extend_from_slice(&(metadata_size as u32).to_be_bytes());
+            header.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+            header.extend_from_slice(&version.to_be_bytes());
+            header.extend_from_slice(&(data_offset as u32).to_be_bytes());
+            header.extend_from_slice(&[endian, 0, 0, 0]);
+        }
+
+        bytes[..header_len].copy_from_slice(&header);
+        bytes
+    }
+
+    #[test]
+    fn rewrites_lengths_padding_and_offsets() {
+        let replacer = Replacer::new(&HashMap::from([
+            ("first".to_string(), "changed".to_string()),
+            ("menu".to_string(), "options screen".to_string()),
+        ]))
+        .unwrap();
+
+        for (version, big_endian) in [(22, false), (17, true), (19, false)] {
+            let original = asset(
+                version,
+                big_endian,
+                &[
+                    table(
+                        "Menu_en",
+                        &[(1, "First item"), (2, "Back to menu")],
+                        big_endian,
+                    ),
+                    table("Credits_en", &[(3, "first")], big_endian),
+                ],
+            );
+            let expected = asset(
+                version,
+                big_endian,
+                &[
+                    table(
+                        "Menu_en",
+                        &[(1, "Changed item"), (2, "Back to options screen")],
+                        big_endian,
+                    ),
+                    table("Credits_en", &[(3, "changed")], big_endian),
+                ],
+            );
+
+            let (count, result) = replace(&replacer, &original).unwrap();
+            assert_eq!(count, 3, "version {version}");
+            assert_eq!(expected, result, "version {version}");
+        }
+    }
+
+    #[test]
+    fn untouched_file_is_kept() {
+        let replacer = Replacer::new(&HashMap::from([(
+            "first".to_string(),
+            "changed".to_string(),
+        )]))
+        .unwrap();
+        let original = asset(22, false, &[table("Menu_en", &[(1, "Options")], false)]);
+        assert_eq!(
+            (0, original.clone()),
+            replace(&replacer, &original).unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_files() {
+        let replacer = Replacer::new(&HashMap::from([(
+            "first".to_string(),
+            "changed".to_string(),
+        )]))
+        .unwrap();
+        let original = asset(22, false, &[table("Menu_en", &[(1, "first")], false)]);
+        for bytes in [&original[..40], b"not a unity file at all".as_slice()] {
+            assert!(matches!(
+                replace(&replacer, bytes),
+                Err(ReplaceError::InvalidAsset(_))
+            ));
+        }
+
+        let mut stripped = original.clone();
+        let type_tree_flag = 48 + "2021.3.0f1\0".len() + 4;
+        stripped[type_tree_flag] = 0;
+        assert!(matches!(
+            replace(&replacer, &stripped),
+            Err(ReplaceError::InvalidAsset(_))
+        ));
+
+        // The length of m_Name now runs past the end of the object.
+        let mut overrun = original.clone();
+        let data_offset = u64::from_be_bytes(original[32..40].try_into().unwrap()) as usize;
+        overrun[data_offset] = 200;
+        assert!(matches!(
+            replace(&replacer, &overrun),
+            Err(ReplaceError::InvalidAsset(_))
+        ));
+
+        // The data offset points into the metadata, with the object start moved up to match.
+        let mut overlap = asset(17, true, &[table("Menu_en", &[(1, "first")], true)]);
+        let metadata_end = 20 + u32::from_be_bytes(overlap[..4].try_into().unwrap()) as usize;
+        let data_offset = u32::from_be_bytes(overlap[12..16].try_into().unwrap());
+        let start_field = metadata_end - 20;
+        overlap[12..16].copy_from_slice(&40u32.to_be_bytes());
+        overlap[start_field..start_field + 4].copy_from_slice(&(data_offset - 40).to_be_bytes());
+        assert!(matches!(
+            replace(&replacer, &overlap),
+            Err(ReplaceError::InvalidAsset(_))
+        ));
+    }
+}
@@ -1,47 +1,42 @@
-use crate::case_matcher::match_case;
+use crate::callback::WordContext;
+use crate::encoding::Encoding;
+use crate::error::ReplaceError;
+use crate::replacer::Replacer;
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::iter;
-
-pub fn text_replace(dictionary: &HashMap<String, String>, content: String) -> (u128, String) {
-    let mut new_content = String::with_capacity(content.len());
-    let mut chars = content.chars().peekable();
-    let mut count = 0;
-    while let Some(ch) = chars.next() {
-        if ch.is_alphabetic() {
-            let word: String = iter::once(ch)
-                .chain(iter::from_fn(|| {
-                    chars.by_ref().next_if(|b| b.is_alphanumeric() || *b == '_')
-                }))
-                .collect();
-
-            let word = match dictionary.get(&word.to_lowercase()) {
-                Some(value) => {
-                    count += 1;
-                    &match_case(&word, value)
-                }
-                None => &word,
-            };
-
-            new_content += word;
-        } else {
-            new_content.push(ch);
-        }
-    }
-
-    (count, new_content)
+
+pub fn text_replace(
+    dictionary: &HashMap<String, String>,
+    content: String,
+) -> Result<(u128, String), ReplaceError> {
+    // Predates `ReplacerBuilder::strict`, so a key that can never match is reported.
+    let replacer = Replacer::builder().strict(true).build(dictionary)?;
+    Ok(replacer.replace_text(&content))
+}
+
+pub fn text_replace_with<'r>(
+    content: &str,
+    replacement: impl FnMut(&str, &WordContext) -> Option<Cow<'r, str>>,
+) -> (u128, String) {
+    let replacer = Replacer::new(&HashMap::new()).expect("an empty dictionary always builds");
+    let (count, bytes) = replacer.replace_with(content.as_bytes(), Encoding::Utf8, replacement);
+    let text = String::from_utf8(bytes).expect("replacing words in UTF-8 keeps it valid");
+    (count, text)
 }
 
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
 
-    use crate::utf8_replacer::text_replace;
+    use crate::error::ReplaceError;
+    use crate::replacer::Replacer;
+    use crate::utf8_replacer::{text_replace, text_replace_with};
 
     #[test]
     fn replaces_text() {
         let map = create_map();
         let content = "lorem pondem first".to_string();
-        let (count, result) = text_replace(&map, content);
+        let (count, result) = text_replace(&map, content).unwrap();
         assert_eq!("lorem pondem changed".to_owned(), result);
         assert_eq!(count, 1);
     }
@@ -50,7 +45,7 @@ mod tests {
     fn works_with_stoppers() {
         let map = create_map();
         let content = "lorem pondem first.".to_string();
-        let (count, result) = text_replace(&map, content);
+        let (count, result) = text_replace(&map, content).unwrap();
         assert_eq!("lorem pondem changed.".to_string(), result);
         assert_eq!(count, 1);
     }
@@ -59,7 +54,7 @@ mod tests {
     fn change_more_words() {
         let map = create_map();
         let content = "first and another.".to_string();
-        let (count, result) = text_replace(&map, content);
+        let (count, result) = text_replace(&map, content).unwrap();
         assert_eq!("changed and something.".to_string(), result);
         assert_eq!(count, 2);
     }
@@ -68,7 +63,7 @@ mod tests {
     fn correctly_works_with_plural() {
         let map = create_map();
         let content = "first and another, anothers.".to_string();
-        let (count, result) = text_replace(&map, content);
+        let (count, result) = text_replace(&map, content).unwrap();
         assert_eq!("changed and something, somethingelse.".to_string(), result);
         assert_eq!(count, 3);
     }
@@ -77,7 +72,7 @@ mod tests {
     fn new_lines_work() {
         let map = create_map();
         let content = "\nfirst and \nAnother, \nANOTHERS.".to_string();
-        let (count, result) = text_replace(&map, content);
+        let (count, result) = text_replace(&map, content).unwrap();
         assert_eq!(
             "\nchanged and \nSomething, \nSOMETHINGELSE.".to_string(),
             result
@@ -89,7 +84,7 @@ mod tests {
     fn capilization() {
         let map = create_map();
         let content = "First Another Capital Anothers".to_string();
-        let (_, result) = text_replace(&map, content);
+        let (_, result) = text_replace(&map, content).unwrap();
         assert_eq!(
             "Changed Something Toonice Somethingelse".to_string(),
             result
@@ -100,7 +95,7 @@ mod tests {
     fn uppercase() {
         let map = create_map();
         let content = "FIRST ANOTHER CAPITAL ANOTHERS".to_string();
-        let (_, result) = text_replace(&map, content);
+        let (_, result) = text_replace(&map, content).unwrap();
         assert_eq!(
             "CHANGED SOMETHING TOONICE SOMETHINGELSE".to_string(),
             result
@@ -111,7 +106,7 @@ mod tests {
     fn lowercase() {
         let map = create_map();
         let content = "first another capital anothers".to_string();
-        let (count, result) = text_replace(&map, content);
+        let (count, result) = text_replace(&map, content).unwrap();
         assert_eq!(
             "changed something toonice somethingelse".to_string(),
             result
@@ -123,7 +118,7 @@ mod tests {
     fn replacment_is_bigger_than_original() {
         let map = create_map();
         let content = "small".to_string();
-        let (count, result) = text_replace(&map, content);
+        let (count, result) = text_replace(&map, content).unwrap();
         assert_eq!("this is bigger than the original".to_string(), result);
         assert_eq!(count, 1);
     }
@@ -132,11 +127,66 @@ mod tests {
     fn replace_asian_and_russian_chars() {
         let map = create_map();
         let content = "1 string m_Localized = \"Chinese (中文)\"\n0 MetadataCollection m_Metadata\n1 string m_Localized = \"Russian (Русский)\"\n0 MetadataCollection m_Metadata".to_string();
-        let (count, result) = text_replace(&map, content.clone());
+        let (count, result) = text_replace(&map, content.clone()).unwrap();
         assert_eq!(content, result);
         assert_eq!(count, 0);
     }
 
+    #[test]
+    fn keeps_non_ascii_case() {
+        let mut map = create_map();
+        map.insert("ärger".into(), "straße".into());
+        let content = "ärger Ärger ÄRGER".to_string();
+        let (count, result) = text_replace(&map, content).unwrap();
+        assert_eq!("straße Straße STRASSE".to_string(), result);
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn keys_that_cannot_match_are_reported() {
+        let mut map = create_map();
+        map.insert("c++".into(), "cpp".into());
+        let content = "first in c++".to_string();
+        assert!(matches!(
+            text_replace(&map, content),
+            Err(ReplaceError::InvalidKey(key)) if key == "c++"
+        ));
+    }
+
+    #[test]
+    fn words_start_after_digits() {
+        let map = create_map();
+        let content = "3first 1first v2first".to_string();
+        let (count, result) = text_replace(&map, content).unwrap();
+        assert_eq!("3changed 1changed v2first".to_string(), result);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn replace_with_callback() {
+        let (count, result) = text_replace_with("v1 FIRST and first", |word, ctx| match word {
+            "v1" => Some("v2".into()),
+            _ if ctx.previous == Some("and") => Some(ctx.match_case("changed").into()),
+            _ => None,
+        });
+        assert_eq!("v2 FIRST and changed", result);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn case_sensitive_keeps_keys_apart() {
+        let mut map = create_map();
+        map.insert("US".into(), "USA".into());
+        map.insert("us".into(), "we".into());
+        let replacer = Replacer::builder()
+            .case_sensitive(true)
+            .build(&map)
+            .unwrap();
+        let (count, result) = replacer.replace_text("Us and US, first First");
+        assert_eq!("Us and USA, changed First", result);
+        assert_eq!(count, 2);
+    }
+
     fn create_map() -> HashMap<String, String> {
         let mut map = HashMap::new();
         map.insert("first".into(), "changed".into());
@@ -1,3 +1,10 @@
+#[cfg(test)]
+use std::ops::Deref;
+#[cfg(test)]
+use std::path::{Path, PathBuf};
+#[cfg(test)]
+use std::{env, fs, process};
+
 pub fn get_le_16_bytes(text: &str) -> Vec<u8> {
     text.encode_utf16().flat_map(|i| i.to_le_bytes()).collect()
 }
@@ -5,3 +12,66 @@ pub fn get_le_16_bytes(text: &str) -> Vec<u8> {
 pub fn get_be_16_bytes(text: &str) -> Vec<u8> {
     text.encode_utf16().flat_map(|i| i.to_be_bytes()).collect()
 }
+
+pub fn get_le_32_bytes(text: &str) -> Vec<u8> {
+    text.chars()
+        .flat_map(|c| (c as u32).to_le_bytes())
+        .collect()
+}
+
+pub fn get_be_32_bytes(text: &str) -> Vec<u8> {
+    text.chars()
+        .flat_map(|c| (c as u32).to_be_bytes())
+        .collect()
+}
+
+/// A directory under the system temp dir for one test, removed again when dropped.
+#[cfg(test)]
+pub(crate) struct TempDir(PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    pub(crate) fn new(name: &str) -> Self {
+        Self::new_in(&env::temp_dir(), name)
+    }
+
+    pub(crate) fn new_in(base: &Path, name: &str) -> Self {
+        let path = base.join(format!("text-replacer-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub(crate) fn write(&self, name: &str, content: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.0.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+
+        fs::write(&path, content).unwrap();
+        path
+    }
+}
+
+#[cfg(test)]
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,356 @@
+use crate::encoding::Encoding;
+use crate::replacer::{Replacer, normalize};
+use crate::scanner;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Issue {
+    /// The key does not start and end with a word character, so no word can ever match it.
+    Unmatchable {
+        key: String,
+    },
+    /// These keys fold to the same lookup key, and only `kept` is used.
+    Duplicate {
+        keys: Vec<String>,
+        kept: String,
+    },
+    /// The tokenizer reads the key as several words joined by something other than whitespace,
+    /// so it only matches as a phrase with exactly that text in between.
+    Split {
+        key: String,
+        words: Vec<String>,
+    },
+    EmptyValue {
+        key: String,
+    },
+    /// The replacement for `key` contains `contains`, which would be replaced on another run.
+    Chain {
+        key: String,
+        contains: String,
+    },
+    /// Replacing these keys over and over never settles, each leads back to the others.
+    Cycle {
+        keys: Vec<String>,
+    },
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::Unmatchable { key } => write!(f, "{key:?} can never match a word"),
+            Issue::Duplicate { keys, kept } => {
+                write!(f, "{keys:?} are the same key, only {kept:?} is used")
+            }
+            Issue::Split { key, words } => write!(f, "{key:?} is split into the words {words:?}"),
+            Issue::EmptyValue { key } => write!(f, "{key:?} is replaced with nothing"),
+            Issue::Chain { key, contains } => {
+                write!(
+                    f,
+                    "the replacement of {key:?} contains the key {contains:?}"
+                )
+            }
+            Issue::Cycle { keys } => write!(f, "{keys:?} replace each other in a cycle"),
+        }
+    }
+}
+
+/// Checks `dictionary` against `replacer`, which has to be built from its matchable keys.
+pub(crate) fn validate(
+    dictionary: &HashMap<String, String>,
+    replacer: &Replacer,
+    case_sensitive: bool,
+) -> Vec<Issue> {
+    let tokenizer = replacer.tokenizer();
+    let entries: BTreeMap<&String, &String> = dictionary.iter().collect();
+    let mut issues = vec![];
+    let mut folded: BTreeMap<String, Vec<&String>> = BTreeMap::new();
+    for (&key, &value) in &entries {
+        let normalized = normalize(key, case_sensitive);
+        if !tokenizer.is_valid_key(&normalized) {
+            issues.push(Issue::Unmatchable { key: key.clone() });
+            continue;
+        }
+
+        let (words, _) = scanner::words(Encoding::Utf8, tokenizer, normalized.as_bytes(), true);
+        let joined = words
+            .windows(2)
+            .any(|pair| !normalized[pair[0].range.end..pair[1].range.start].contains(' '));
+        if joined {
+            issues.push(Issue::Split {
+                key: key.clone(),
+                words: words.into_iter().map(|word| word.text).collect(),
+            });
+        }
+
+        if value.is_empty() {
+            issues.push(Issue::EmptyValue { key: key.clone() });
+        }
+
+        folded.entry(normalized).or_default().push(key);
+    }
+
+    for (normalized, keys) in &folded {
+        if keys.len() > 1 {
+            let kept = keys
+                .iter()
+                .find(|key| **key == normalized)
+                .unwrap_or(&keys[0]);
+            issues.push(Issue::Duplicate {
+                keys: keys.iter().map(|key| key.to_string()).collect(),
+                kept: kept.to_string(),
+            });
+        }
+    }
+
+    let mut chains: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for (&key, &value) in &entries {
+        if !tokenizer.is_valid_key(&normalize(key, case_sensitive)) {
+            continue;
+        }
+
+        let (words, _) = scanner::words(Encoding::Utf8, tokenizer, value.as_bytes(), true);
+        let (edits, _) = replacer.edits(&words, |range| Some(value[range].to_string()), true);
+        for edit in edits {
+            if chains.entry(key).or_default().insert(edit.key) {
+                issues.push(Issue::Chain {
+                    key: key.clone(),
+                    contains: edit.key.to_string(),
+                });
+            }
+        }
+    }
+
+    for keys in cycles(&chains) {
+        issues.push(Issue::Cycle {
+            keys: keys.into_iter().map(String::from).collect(),
+        });
+    }
+
+    issues
+}
+
+// Tarjan's strongly connected components, keeping the ones that loop. Dictionaries can chain
+// thousands of keys, so the search keeps its own stack of the keys being visited, each with the
+// keys it still has to follow, instead of recursing.
+fn cycles<'a>(chains: &BTreeMap<&'a str, BTreeSet<&'a str>>) -> Vec<Vec<&'a str>> {
+    #[derive(Default)]
+    struct State<'a> {
+        index: HashMap<&'a str, usize>,
+        low: HashMap<&'a str, usize>,
+        stack: Vec<&'a str>,
+        on_stack: BTreeSet<&'a str>,
+    }
+
+    impl<'a> State<'a> {
+        fn enter(&mut self, key: &'a str) {
+            let index = self.index.len();
+            self.index.insert(key, index);
+            self.low.insert(key, index);
+            self.stack.push(key);
+            self.on_stack.insert(key);
+        }
+
+        fn lower(&mut self, key: &'a str, to: usize) {
+            let low = self.low[key].min(to);
+            self.low.insert(key, low);
+        }
+    }
+
+    let follow = |key: &'a str| chains.get(key).into_iter().flatten().copied();
+    let mut state = State::default();
+    let mut cycles = vec![];
+    for &root in chains.keys() {
+        if state.index.contains_key(root) {
+            continue;
+        }
+
+        state.enter(root);
+        let mut visiting = vec![(root, follow(root))];
+        while let Some((key, next_keys)) = visiting.last_mut() {
+            let key = *key;
+            if let Some(next) = next_keys.next() {
+                if !state.index.contains_key(next) {
+                    state.enter(next);
+                    visiting.push((next, follow(next)));
+                } else if state.on_stack.contains(next) {
+                    state.lower(key, state.index[next]);
+                }
+
+                continue;
+            }
+
+            visiting.pop();
+            if let Some((parent, _)) = visiting.last() {
+                state.lower(parent, state.low[key]);
+            }
+
+            if state.low[key] == state.index[key] {
+                let mut component = vec![];
+                while let Some(member) = state.stack.pop() {
+                    state.on_stack.remove(member);
+                    component.push(member);
+                    if member == key {
+                        break;
+                    }
+                }
+
+                let loops = chains.get(key).is_some_and(|next| next.contains(key));
+                if component.len() > 1 || loops {
+                    component.sort();
+                    cycles.push(component);
+                }
+            }
+        }
+    }
+
+    cycles.sort();
+    cycles
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+    use crate::replacer::Replacer;
+    use crate::tokenizer::Tokenizer;
+    use crate::validate::{Issue, cycles};
+
+    #[test]
+    fn clean_dictionary() {
+        let map = create_map(&[("first", "changed"), ("sign in", "log in")]);
+        assert!(Replacer::builder().validate(&map).is_empty());
+    }
+
+    #[test]
+    fn unmatchable_and_empty() {
+        let map = create_map(&[("c++", "cpp"), ("-dash", "dash"), ("remove", "")]);
+        assert_eq!(
+            vec![
+                Issue::Unmatchable {
+                    key: "-dash".into()
+                },
+                Issue::Unmatchable { key: "c++".into() },
+                Issue::EmptyValue {
+                    key: "remove".into()
+                },
+            ],
+            Replacer::builder().validate(&map)
+        );
+    }
+
+    #[test]
+    fn duplicates_after_folding() {
+        let map = create_map(&[
+            ("US", "a"),
+            ("Us", "b"),
+            ("us", "c"),
+            ("Sign  In", "d"),
+            ("sign in", "e"),
+        ]);
+        assert_eq!(
+            vec![
+                Issue::Duplicate {
+                    keys: vec!["Sign  In".into(), "sign in".into()],
+                    kept: "sign in".into()
+                },
+                Issue::Duplicate {
+                    keys: vec!["US".into(), "Us".into(), "us".into()],
+                    kept: "us".into()
+                },
+            ],
+            Replacer::builder().validate(&map)
+        );
+
+        let issues = Replacer::builder().case_sensitive(true).validate(&map);
+        assert_eq!(Vec::<Issue>::new(), issues);
+    }
+
+    #[test]
+    fn keys_split_by_the_tokenizer() {
+        let map = create_map(&[("e-mail", "email"), ("don't", "do not"), ("m_name", "name")]);
+        assert_eq!(
+            vec![
+                Issue::Split {
+                    key: "don't".into(),
+                    words: vec!["don".into(), "t".into()]
+                },
+                Issue::Split {
+                    key: "e-mail".into(),
+                    words: vec!["e".into(), "mail".into()]
+                },
+            ],
+            Replacer::builder().validate(&map)
+        );
+
+        let tokenizer = Tokenizer::new().join_chars(&['-', '\'']).extra_chars(&[]);
+        let issues = Replacer::builder().tokenizer(tokenizer).validate(&map);
+        assert_eq!(
+            vec![Issue::Split {
+                key: "m_name".into(),
+                words: vec!["m".into(), "name".into()]
+            }],
+            issues
+        );
+    }
+
+    #[test]
+    fn chains_and_cycles() {
+        let map = create_map(&[
+            ("cat", "dog"),
+            ("dog", "big cat"),
+            ("big", "large"),
+            ("apple", "apple pie"),
+            ("first", "changed"),
+        ]);
+        assert_eq!(
+            vec![
+                Issue::Chain {
+                    key: "apple".into(),
+                    contains: "apple".into()
+                },
+                Issue::Chain {
+                    key: "cat".into(),
+                    contains: "dog".into()
+                },
+                Issue::Chain {
+                    key: "dog".into(),
+                    contains: "big".into()
+                },
+                Issue::Chain {
+                    key: "dog".into(),
+                    contains: "cat".into()
+                },
+                Issue::Cycle {
+                    keys: vec!["apple".into()]
+                },
+                Issue::Cycle {
+                    keys: vec!["cat".into(), "dog".into()]
+                },
+            ],
+            Replacer::builder().validate(&map)
+        );
+    }
+
+    #[test]
+    fn long_chains_do_not_recurse() {
+        let keys: Vec<String> = (0..50_000).map(|index| format!("k{index}")).collect();
+        let mut chains: BTreeMap<&str, BTreeSet<&str>> = keys
+            .windows(2)
+            .map(|pair| (pair[0].as_str(), BTreeSet::from([pair[1].as_str()])))
+            .collect();
+        assert!(cycles(&chains).is_empty());
+
+        chains.insert(&keys[keys.len() - 1], BTreeSet::from([keys[0].as_str()]));
+        let found = cycles(&chains);
+        assert_eq!(1, found.len());
+        assert_eq!(keys.len(), found[0].len());
+    }
+
+    fn create_map(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+}
@@ -0,0 +1,410 @@
+use crate::encoding::{Encoding, Unit};
+use crate::error::ReplaceError;
+use crate::file::{self, FileCount, FileError, FileOptions};
+use crate::replacer::Replacer;
+use globset::{Glob, GlobMatcher, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Which files under a root get replaced. Patterns are globs matched against the path relative
+/// to the root, so "*.txt" matches text files in every directory and "docs/**" one directory.
+#[derive(Clone, Debug)]
+pub struct WalkOptions {
+    include: Vec<String>,
+    exclude: Vec<String>,
+    encodings: Vec<(String, Encoding)>,
+    ignore_files: bool,
+    ignore_names: Vec<String>,
+    hidden_files: bool,
+    file: FileOptions,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        Self {
+            include: vec![],
+            exclude: vec![],
+            encodings: vec![],
+            ignore_files: true,
+            ignore_names: vec![],
+            hidden_files: false,
+            file: FileOptions::default(),
+        }
+    }
+}
+
+impl WalkOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Once any pattern is included, files matching none of them are skipped.
+    pub fn include(mut self, pattern: &str) -> Self {
+        self.include.push(pattern.to_string());
+        self
+    }
+
+    pub fn exclude(mut self, pattern: &str) -> Self {
+        self.exclude.push(pattern.to_string());
+        self
+    }
+
+    /// Files matching `pattern` are read as `encoding`, the first matching pattern wins. Other
+    /// files use the encoding of the file options, or have theirs detected.
+    pub fn encoding(mut self, pattern: &str, encoding: Encoding) -> Self {
+        self.encodings.push((pattern.to_string(), encoding));
+        self
+    }
+
+    /// Whether .gitignore, .ignore and the other ignore files are respected.
+    pub fn ignore_files(mut self, respect: bool) -> Self {
+        self.ignore_files = respect;
+        self
+    }
+
+    /// Adds another ignore file name, read with the same syntax as .gitignore.
+    pub fn ignore_file_name(mut self, name: &str) -> Self {
+        self.ignore_names.push(name.to_string());
+        self
+    }
+
+    /// Whether files and directories whose name starts with a dot are walked. They are skipped
+    /// by default.
+    pub fn hidden_files(mut self, walk: bool) -> Self {
+        self.hidden_files = walk;
+        self
+    }
+
+    pub fn file_options(mut self, options: FileOptions) -> Self {
+        self.file = options;
+        self
+    }
+}
+
+/// Files that cannot be read or written are listed with their error, and the walk goes on.
+pub(crate) fn replace(
+    replacer: &Replacer,
+    root: &Path,
+    options: &WalkOptions,
+) -> Result<(Vec<FileCount>, Vec<FileError>), ReplaceError> {
+    let include = glob_set(&options.include)?;
+    let exclude = glob_set(&options.exclude)?;
+    let encodings = options
+        .encodings
+        .iter()
+        .map(|(pattern, encoding)| Ok((glob(pattern)?.compile_matcher(), *encoding)))
+        .collect::<Result<Vec<(GlobMatcher, Encoding)>, ReplaceError>>()?;
+
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .hidden(!options.hidden_files)
+        .parents(options.ignore_files)
+        .ignore(options.ignore_files)
+        .git_ignore(options.ignore_files)
+        .git_exclude(options.ignore_files)
+        .git_global(false)
+        .require_git(false)
+        .sort_by_file_name(|a, b| a.cmp(b));
+    if options.ignore_files {
+        for name in &options.ignore_names {
+            builder.add_custom_ignore_filename(name);
+        }
+    }
+
+    let mut counts = vec![];
+    let mut failed = vec![];
+    for entry in builder.build() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(error) => {
+                let path = match &error {
+                    ignore::Error::WithPath { path, .. } => path.clone(),
+                    _ => root.to_path_buf(),
+                };
+                let error = ReplaceError::Io(io::Error::other(error));
+                failed.push(FileError { path, error });
+                continue;
+            }
+        };
+
+        if !entry.file_type().is_some_and(|kind| kind.is_file()) {
+            continue;
+        }
+
+        let path = entry.path();
+        let relative = match path.strip_prefix(root) {
+            Ok(relative) if !relative.as_os_str().is_empty() => relative,
+            _ => path,
+        };
+
+        if (!options.include.is_empty() && !include.is_match(relative))
+            || exclude.is_match(relative)
+        {
+            continue;
+        }
+
+        let file_options = match encodings.iter().find(|(glob, _)| glob.is_match(relative)) {
+            Some(&(_, encoding)) => options.file.clone().encoding(encoding),
+            None => options.file.clone(),
+        };
+
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(error) => {
+                failed.push(FileError {
+                    path: path.to_path_buf(),
+                    error: error.into(),
+                });
+                continue;
+            }
+        };
+
+        if !file_options.has_encoding() && is_binary(&bytes) {
+            continue;
+        }
+
+        match file::rewrite(replacer, path, path, &bytes, &file_options) {
+            Ok(count) => counts.push(count),
+            Err(error) => failed.push(FileError {
+                path: path.to_path_buf(),
+                error,
+            }),
+        }
+    }
+
+    Ok((counts, failed))
+}
+
+// NUL bytes alone do not tell binary data apart, UTF-16 and UTF-32 text is full of them. So the
+// start of the file is decoded as whatever it looks like, and any control character, NUL
+// included, or invalid unit marks it as binary. Non-ASCII bytes of single byte text are fine.
+fn is_binary(bytes: &[u8]) -> bool {
+    let mut sample = &bytes[..bytes.len().min(8192)];
+    let encoding = Encoding::detect(sample);
+    while !sample.is_empty() {
+        match encoding.decode(sample) {
+            Unit::Char(ch, len) if !ch.is_control() || ch.is_ascii_whitespace() => {
+                sample = &sample[len..]
+            }
+            Unit::Invalid if encoding == Encoding::Ascii => sample = &sample[1..],
+            Unit::Incomplete => break,
+            _ => return true,
+        }
+    }
+
+    false
+}
+
+fn glob(pattern: &str) -> Result<Glob, ReplaceError> {
+    Glob::new(pattern).map_err(|error| ReplaceError::InvalidPattern(error.to_string()))
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet, ReplaceError> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(glob(pattern)?);
+    }
+
+    builder
+        .build()
+        .map_err(|error| ReplaceError::InvalidPattern(error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::Path;
+
+    use crate::encoding::Encoding;
+    use crate::error::ReplaceError;
+    use crate::file::{FileCount, FileOptions};
+    use crate::replacer::Replacer;
+    use crate::utils::{TempDir, get_le_16_bytes};
+    use crate::walk::WalkOptions;
+
+    #[test]
+    fn replaces_every_text_file() {
+        let root = create_tree("all");
+        let counts = replace(&root, &WalkOptions::new());
+        assert_eq!(
+            vec![
+                count(&root, "a.txt", Encoding::Utf8, 1),
+                count(&root, "notes.md", Encoding::Utf8, 2),
+                count(&root, "sub/b.txt", Encoding::Utf8, 1),
+                count(&root, "sub/wide.txt", Encoding::Utf16Le, 1),
+            ],
+            counts
+        );
+        assert_eq!("changed", fs::read_to_string(root.join("a.txt")).unwrap());
+        assert_eq!(
+            b"first\0\0\0\x01\x02\0\0\0\x03".to_vec(),
+            fs::read(root.join("sub/data.bin")).unwrap()
+        );
+        assert_eq!(
+            "first",
+            fs::read_to_string(root.join("build/out.txt")).unwrap()
+        );
+        assert_eq!(
+            "first",
+            fs::read_to_string(root.join(".hidden.txt")).unwrap()
+        );
+    }
+
+    #[test]
+    fn include_and_exclude() {
+        let root = create_tree("globs");
+        let options = WalkOptions::new().include("*.txt").exclude("sub/wide*");
+        let counts = replace(&root, &options);
+        assert_eq!(
+            vec![
+                count(&root, "a.txt", Encoding::Utf8, 1),
+                count(&root, "sub/b.txt", Encoding::Utf8, 1),
+            ],
+            counts
+        );
+        assert_eq!(
+            "first another",
+            fs::read_to_string(root.join("notes.md")).unwrap()
+        );
+    }
+
+    #[test]
+    fn ignore_files_can_be_turned_off() {
+        let root = create_tree("no-ignore");
+        let options = WalkOptions::new().ignore_files(false).include("build/**");
+        let counts = replace(&root, &options);
+        assert_eq!(
+            vec![count(&root, "build/out.txt", Encoding::Utf8, 1)],
+            counts
+        );
+    }
+
+    #[test]
+    fn custom_ignore_file() {
+        let root = create_tree("custom-ignore");
+        fs::write(root.join(".replacerignore"), "*.md\nsub/\n").unwrap();
+        let options = WalkOptions::new().ignore_file_name(".replacerignore");
+        let counts = replace(&root, &options);
+        assert_eq!(vec![count(&root, "a.txt", Encoding::Utf8, 1)], counts);
+    }
+
+    #[test]
+    fn encoding_per_pattern() {
+        let root = create_tree("encodings");
+        let options = WalkOptions::new()
+            .include("sub/*")
+            .encoding("*.bin", Encoding::Ascii)
+            .encoding("*.txt", Encoding::Utf8);
+        let counts = replace(&root, &options);
+        assert_eq!(
+            vec![
+                count(&root, "sub/b.txt", Encoding::Utf8, 1),
+                count(&root, "sub/data.bin", Encoding::Ascii, 1),
+                count(&root, "sub/wide.txt", Encoding::Utf8, 0),
+            ],
+            counts
+        );
+        assert_eq!(
+            b"changed\0\0\0\x01\x02\0\0\0\x03".to_vec(),
+            fs::read(root.join("sub/data.bin")).unwrap()
+        );
+    }
+
+    #[test]
+    fn dry_run_diffs_every_changed_file() {
+        let root = create_tree("dry-run");
+        let options = WalkOptions::new()
+            .include("*.txt")
+            .file_options(FileOptions::new().dry_run(true));
+        let counts = replace(&root, &options);
+        let diffs: Vec<bool> = counts.iter().map(|file| file.diff.is_some()).collect();
+        assert_eq!(vec![true, true, true], diffs);
+        assert!(counts[2].diff.as_ref().unwrap().contains("+changed"));
+        assert_eq!("first", fs::read_to_string(root.join("a.txt")).unwrap());
+    }
+
+    #[test]
+    fn hidden_files_can_be_walked() {
+        let root = create_tree("hidden");
+        let options = WalkOptions::new().hidden_files(true).include(".hidden*");
+        let counts = replace(&root, &options);
+        assert_eq!(vec![count(&root, ".hidden.txt", Encoding::Utf8, 1)], counts);
+        assert_eq!(
+            "changed",
+            fs::read_to_string(root.join(".hidden.txt")).unwrap()
+        );
+    }
+
+    #[test]
+    fn failed_files_do_not_stop_the_walk() {
+        let root = create_tree("failed");
+        // The backup of a.txt cannot be written over a directory.
+        fs::create_dir(root.join("a.txt.bak")).unwrap();
+        let replacer = Replacer::new(&create_map()).unwrap();
+        let options = WalkOptions::new()
+            .include("*.txt")
+            .file_options(FileOptions::new().backup(true));
+        let (counts, failed) = replacer.replace_dir(&root, &options).unwrap();
+        assert_eq!(
+            vec![
+                count(&root, "sub/b.txt", Encoding::Utf8, 1),
+                count(&root, "sub/wide.txt", Encoding::Utf16Le, 1),
+            ],
+            counts
+        );
+        assert_eq!(1, failed.len());
+        assert_eq!(root.join("a.txt"), failed[0].path);
+        assert!(matches!(failed[0].error, ReplaceError::Io(_)));
+        assert_eq!("first", fs::read_to_string(root.join("a.txt")).unwrap());
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        let root = create_tree("invalid");
+        let replacer = Replacer::new(&create_map()).unwrap();
+        let result = replacer.replace_dir(&root, &WalkOptions::new().include("a[.txt"));
+        assert!(matches!(result, Err(ReplaceError::InvalidPattern(_))));
+    }
+
+    fn replace(root: &Path, options: &WalkOptions) -> Vec<FileCount> {
+        let replacer = Replacer::new(&create_map()).unwrap();
+        let (counts, failed) = replacer.replace_dir(root, options).unwrap();
+        assert!(failed.is_empty(), "{failed:?}");
+        counts
+    }
+
+    fn count(root: &Path, path: &str, encoding: Encoding, count: u128) -> FileCount {
+        FileCount {
+            path: root.join(path),
+            encoding,
+            count,
+            diff: None,
+        }
+    }
+
+    fn create_tree(name: &str) -> TempDir {
+        let root = TempDir::new(&format!("walk-{name}"));
+        root.write(".gitignore", "build/\n");
+        root.write(".hidden.txt", "first");
+        root.write("a.txt", "first");
+        root.write("notes.md", "first another");
+        root.write("build/out.txt", "first");
+        root.write("sub/b.txt", "First");
+        root.write("sub/data.bin", b"first\0\0\0\x01\x02\0\0\0\x03");
+        root.write(
+            "sub/wide.txt",
+            [vec![0xFF, 0xFE], get_le_16_bytes("first")].concat(),
+        );
+        root
+    }
+
+    fn create_map() -> HashMap<String, String> {
+        let mut map = HashMap::new();
+        map.insert("first".into(), "changed".into());
+        map.insert("another".into(), "something".into());
+        map
+    }
+}
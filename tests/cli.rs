@@ -0,0 +1,63 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+#[test]
+fn dash_reads_stdin_and_writes_stdout() {
+    let dictionary = Dictionary::new("dash");
+    let output = run(
+        &[dictionary.path.to_str().unwrap(), "-"],
+        b"first and First\n",
+    );
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(b"changed and Changed\n".as_slice(), output.stdout);
+    assert_eq!(
+        "-: 2 replacements\n",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn no_files_reads_stdin() {
+    let dictionary = Dictionary::new("stdin");
+    let output = run(&[dictionary.path.to_str().unwrap()], b"first");
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(b"changed".as_slice(), output.stdout);
+}
+
+fn run(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_text-replacer"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+// The crate's own test helpers are not visible from here, so this one cleans up after itself.
+struct Dictionary {
+    dir: PathBuf,
+    path: PathBuf,
+}
+
+impl Dictionary {
+    fn new(name: &str) -> Self {
+        let dir =
+            std::env::temp_dir().join(format!("text-replacer-cli-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("dict.json");
+        fs::write(&path, r#"{"first": "changed"}"#).unwrap();
+        Self { dir, path }
+    }
+}
+
+impl Drop for Dictionary {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}